@@ -0,0 +1,23 @@
+// what a decode table holds for a byte that isn't in the alphabet
+pub const INVALID: u8 = 0xff;
+
+// a table from every byte to its value, so decoding doesn't have to search
+// the alphabet for each character
+pub const fn decode_table<const N: usize>(chars: &[u8; N]) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < N {
+        table[chars[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+#[test]
+fn test_decode_table() {
+    let table = decode_table(b"abc");
+    assert_eq!(table[b'a' as usize], 0);
+    assert_eq!(table[b'c' as usize], 2);
+    assert_eq!(table[b'd' as usize], INVALID);
+    assert_eq!(table[0xff], INVALID);
+}
@@ -0,0 +1,186 @@
+use crate::alphabet::{decode_table, INVALID};
+use crate::error::{DecodeError, DecodeType, Error, Result};
+
+// Ascii85 as btoa and PostScript write it: '!' to 'u' for the digits, with a
+// whole group of zeros shortened to 'z'
+const FIRST_CHAR: u8 = b'!';
+const LAST_CHAR: u8 = b'u';
+const ZERO_GROUP: u8 = b'z';
+
+// ZeroMQ's Z85, which avoids quotes and backslashes so it can go in source
+// code, and has no shortcuts
+const Z85_CHARS: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+// Ascii85's digits are a range, so only Z85 needs a table
+const Z85_TABLE: [u8; 256] = decode_table(Z85_CHARS);
+
+fn error(decode_type: DecodeType, msg: &str) -> Error {
+    DecodeError::new(decode_type, msg).into()
+}
+
+fn digits(mut group: u32) -> [u8; 5] {
+    let mut digits = [0u8; 5];
+    for digit in digits.iter_mut().rev() {
+        *digit = (group % 85) as u8;
+        group /= 85;
+    }
+    digits
+}
+
+// five digits back into a group, or None if they come to more than 32 bits
+fn group(digits: &[u8; 5]) -> Option<u32> {
+    digits.iter().try_fold(0u32, |group, digit| {
+        group.checked_mul(85)?.checked_add(*digit as u32)
+    })
+}
+
+pub fn encode(buffer: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(buffer.len().div_ceil(4) * 5);
+
+    for chunk in buffer.chunks(4) {
+        let mut bytes = [0u8; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        let group = u32::from_be_bytes(bytes);
+
+        if group == 0 && chunk.len() == 4 {
+            encoded.push(ZERO_GROUP);
+        } else {
+            // a group of n bytes only needs n + 1 digits to come back
+            let digits = digits(group);
+            encoded.extend(digits[..chunk.len() + 1].iter().map(|d| d + FIRST_CHAR));
+        }
+    }
+
+    encoded
+}
+
+// whitespace is skipped, and the <~ ~> delimiters are optional
+pub fn decode(buffer: &[u8]) -> Result<Vec<u8>> {
+    let mut input: &[u8] = buffer.trim_ascii();
+    if let Some(inner) = input.strip_prefix(b"<~") {
+        input = inner
+            .strip_suffix(b"~>")
+            .ok_or_else(|| error(DecodeType::Ascii85, "buffer isn't closed with ~>"))?;
+    }
+
+    let mut decoded = Vec::with_capacity(input.len() / 5 * 4 + 4);
+    let mut pending = Vec::with_capacity(5);
+    for c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+        match *c {
+            ZERO_GROUP if pending.is_empty() => decoded.extend_from_slice(&[0; 4]),
+            FIRST_CHAR..=LAST_CHAR => pending.push(c - FIRST_CHAR),
+            _ => {
+                return Err(error(
+                    DecodeType::Ascii85,
+                    "buffer contains invalid ascii85 characters",
+                ))
+            }
+        }
+
+        if pending.len() == 5 {
+            let digits: [u8; 5] = pending[..].try_into().expect("five digits");
+            pending.clear();
+            let group = group(&digits)
+                .ok_or_else(|| error(DecodeType::Ascii85, "buffer has a group over 32 bits"))?;
+            decoded.extend_from_slice(&group.to_be_bytes());
+        }
+    }
+
+    // a short last group was padded with zeros before it was encoded, so
+    // it's padded with the top digit to round back up to what was there
+    match pending.len() {
+        0 => {}
+        1 => {
+            return Err(error(
+                DecodeType::Ascii85,
+                "buffer ends with a lone character",
+            ))
+        }
+        len => {
+            let mut digits = [LAST_CHAR - FIRST_CHAR; 5];
+            digits[..len].copy_from_slice(&pending);
+            let group = group(&digits)
+                .ok_or_else(|| error(DecodeType::Ascii85, "buffer has a group over 32 bits"))?;
+            decoded.extend_from_slice(&group.to_be_bytes()[..len - 1]);
+        }
+    }
+
+    Ok(decoded)
+}
+
+pub fn encode_z85(buffer: &[u8]) -> Result<Vec<u8>> {
+    if !buffer.len().is_multiple_of(4) {
+        // Z85 only encodes whole groups of 4 bytes
+        return Err(Error::LengthMismatch(
+            buffer.len(),
+            buffer.len().next_multiple_of(4),
+        ));
+    }
+
+    Ok(buffer
+        .chunks(4)
+        .flat_map(|chunk| {
+            let group = u32::from_be_bytes(chunk.try_into().expect("chunks of 4"));
+            digits(group).map(|digit| Z85_CHARS[digit as usize])
+        })
+        .collect())
+}
+
+pub fn decode_z85(buffer: &[u8]) -> Result<Vec<u8>> {
+    if !buffer.len().is_multiple_of(5) {
+        return Err(error(
+            DecodeType::Z85,
+            "buffer length isn't a multiple of 5",
+        ));
+    }
+
+    let mut decoded = Vec::with_capacity(buffer.len() / 5 * 4);
+    for chunk in buffer.chunks(5) {
+        let mut digits = [0u8; 5];
+        for (digit, c) in digits.iter_mut().zip(chunk) {
+            *digit = Z85_TABLE[*c as usize];
+            if *digit == INVALID {
+                return Err(error(
+                    DecodeType::Z85,
+                    "buffer contains invalid z85 characters",
+                ));
+            }
+        }
+        let group = group(&digits)
+            .ok_or_else(|| error(DecodeType::Z85, "buffer has a group over 32 bits"))?;
+        decoded.extend_from_slice(&group.to_be_bytes());
+    }
+
+    Ok(decoded)
+}
+
+#[test]
+fn test_ascii85() {
+    assert_eq!(encode(b"Man "), b"9jqo^".to_vec());
+    assert_eq!(encode(b"sure."), b"F*2M7/c".to_vec());
+    assert_eq!(decode(b"<~F*2M7/c~>").unwrap(), b"sure.".to_vec());
+    assert_eq!(encode(&[0, 0, 0, 0, 1]), b"z!<".to_vec());
+    assert_eq!(decode(b"z !<").unwrap(), vec![0, 0, 0, 0, 1]);
+    assert!(decode(b"s8W-\"").is_err());
+    assert!(decode(b"9jqo^F").is_err());
+
+    let bytes: Vec<u8> = (0..=255).collect();
+    for len in 0..8 {
+        assert_eq!(
+            decode(&encode(&bytes[..len])).unwrap(),
+            bytes[..len].to_vec()
+        );
+    }
+    assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+
+    // the test vector from the Z85 spec
+    let frame = [0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+    assert_eq!(encode_z85(&frame).unwrap(), b"HelloWorld".to_vec());
+    assert_eq!(decode_z85(b"HelloWorld").unwrap(), frame.to_vec());
+    assert!(matches!(
+        encode_z85(&frame[..5]),
+        Err(Error::LengthMismatch(5, 8))
+    ));
+    assert!(decode_z85(b"Hello\"orld").is_err());
+}
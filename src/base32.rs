@@ -0,0 +1,119 @@
+use crate::alphabet::{decode_table, INVALID};
+use crate::error::{DecodeError, DecodeType, Result};
+
+// RFC 4648 section 6, and the extended hex alphabet of section 7, which
+// keeps the sort order of the bytes it encodes
+const STANDARD_CHARS: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const HEX_CHARS: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+// lowercase is taken for uppercase
+const fn with_lowercase(mut table: [u8; 256]) -> [u8; 256] {
+    let mut c = b'a';
+    while c <= b'z' {
+        table[c as usize] = table[c.to_ascii_uppercase() as usize];
+        c += 1;
+    }
+    table
+}
+
+const STANDARD_TABLE: [u8; 256] = with_lowercase(decode_table(STANDARD_CHARS));
+const HEX_TABLE: [u8; 256] = with_lowercase(decode_table(HEX_CHARS));
+
+// the number of characters a group of 1 to 5 bytes encodes to
+const GROUP_CHARS: [usize; 6] = [0, 2, 4, 5, 7, 8];
+
+fn error(msg: &str) -> DecodeError {
+    DecodeError::new(DecodeType::Base32, msg)
+}
+
+fn encode_with(buffer: &[u8], chars: &[u8; 32]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(buffer.len().div_ceil(5) * 8);
+
+    for chunk in buffer.chunks(5) {
+        let mut group = [0u8; 8];
+        group[3..3 + chunk.len()].copy_from_slice(chunk);
+        let group = u64::from_be_bytes(group);
+
+        for i in 0..GROUP_CHARS[chunk.len()] {
+            encoded.push(chars[(group >> (35 - 5 * i)) as usize & 0x1f]);
+        }
+        encoded.resize(encoded.len().next_multiple_of(8), b'=');
+    }
+
+    encoded
+}
+
+// padding is optional, and lowercase is taken for uppercase
+fn decode_with(buffer: &[u8], table: &[u8; 256]) -> Result<Vec<u8>> {
+    let padded_by = buffer.iter().rev().take_while(|c| **c == b'=').count();
+    let unpadded = &buffer[..buffer.len() - padded_by];
+    if padded_by > 0 && !buffer.len().is_multiple_of(8) {
+        return Err(error("buffer length isn't a multiple of 8").into());
+    }
+
+    let mut decoded = Vec::with_capacity(unpadded.len() / 8 * 5 + 4);
+    for chunk in unpadded.chunks(8) {
+        let len = GROUP_CHARS
+            .iter()
+            .position(|chars| *chars == chunk.len())
+            .ok_or_else(|| error("buffer length leaves a partial character"))?;
+
+        let mut group = 0u64;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = table[*c as usize];
+            if value == INVALID {
+                return Err(error("buffer contains invalid base32 characters").into());
+            }
+            group |= (value as u64) << (35 - 5 * i);
+        }
+        decoded.extend_from_slice(&group.to_be_bytes()[3..3 + len]);
+    }
+
+    Ok(decoded)
+}
+
+pub fn encode(buffer: &[u8]) -> Vec<u8> {
+    encode_with(buffer, STANDARD_CHARS)
+}
+
+pub fn decode(buffer: &[u8]) -> Result<Vec<u8>> {
+    decode_with(buffer, &STANDARD_TABLE)
+}
+
+pub fn encode_hex(buffer: &[u8]) -> Vec<u8> {
+    encode_with(buffer, HEX_CHARS)
+}
+
+pub fn decode_hex(buffer: &[u8]) -> Result<Vec<u8>> {
+    decode_with(buffer, &HEX_TABLE)
+}
+
+#[test]
+fn test_base32() {
+    // the test vectors from RFC 4648 section 10
+    let vectors: [(&[u8], &[u8], &[u8]); 7] = [
+        (b"", b"", b""),
+        (b"f", b"MY======", b"CO======"),
+        (b"fo", b"MZXQ====", b"CPNG===="),
+        (b"foo", b"MZXW6===", b"CPNMU==="),
+        (b"foob", b"MZXW6YQ=", b"CPNMUOG="),
+        (b"fooba", b"MZXW6YTB", b"CPNMUOJ1"),
+        (b"foobar", b"MZXW6YTBOI======", b"CPNMUOJ1E8======"),
+    ];
+    for (plain, standard, hex) in vectors {
+        assert_eq!(encode(plain), standard.to_vec());
+        assert_eq!(decode(standard).unwrap(), plain.to_vec());
+        assert_eq!(encode_hex(plain), hex.to_vec());
+        assert_eq!(decode_hex(hex).unwrap(), plain.to_vec());
+    }
+
+    assert_eq!(decode(b"mzxw6ytboi").unwrap(), b"foobar".to_vec());
+    assert_eq!(decode_hex(b"cpnmuoj1e8").unwrap(), b"foobar".to_vec());
+    assert!(decode(b"MZXW6YT1").is_err());
+    assert!(decode(b"MZXW6YTBO").is_err());
+    assert!(decode(b"MZXW6===8").is_err());
+    assert!(matches!(
+        decode(b"MZ1W6===").unwrap_err(),
+        crate::Error::Decode(e) if e.decode_type() == DecodeType::Base32
+    ));
+}
@@ -0,0 +1,121 @@
+use crate::alphabet::{decode_table, INVALID};
+use crate::error::{DecodeError, DecodeType, Result};
+use openssl::hash::{hash, MessageDigest};
+
+// bitcoin's alphabet, which leaves out 0, O, I and l
+const CHARS: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const CHECKSUM_LEN: usize = 4;
+
+const TABLE: [u8; 256] = decode_table(CHARS);
+
+fn error(msg: &str) -> DecodeError {
+    DecodeError::new(DecodeType::Base58, msg)
+}
+
+// the whole buffer is one big-endian number, converted a digit at a time.
+// leading zero bytes would vanish from the number, so each is kept as a
+// leading '1'
+pub fn encode(buffer: &[u8]) -> Vec<u8> {
+    let zeros = buffer.iter().take_while(|byte| **byte == 0).count();
+
+    // little-endian base 58 digits
+    let mut digits: Vec<u8> = vec![];
+    for byte in &buffer[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut encoded = vec![CHARS[0]; zeros];
+    encoded.extend(digits.iter().rev().map(|digit| CHARS[*digit as usize]));
+    encoded
+}
+
+pub fn decode(buffer: &[u8]) -> Result<Vec<u8>> {
+    let ones = buffer.iter().take_while(|c| **c == CHARS[0]).count();
+
+    // little-endian bytes
+    let mut bytes: Vec<u8> = vec![];
+    for c in &buffer[ones..] {
+        let digit = TABLE[*c as usize];
+        if digit == INVALID {
+            return Err(error("buffer contains invalid base58 characters").into());
+        }
+        let mut carry = digit as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut decoded = vec![0u8; ones];
+    decoded.extend(bytes.iter().rev());
+    Ok(decoded)
+}
+
+fn checksum(payload: &[u8]) -> Result<Vec<u8>> {
+    let once = hash(MessageDigest::sha256(), payload)?;
+    let twice = hash(MessageDigest::sha256(), &once)?;
+    Ok(twice[..CHECKSUM_LEN].to_vec())
+}
+
+// Base58Check: the payload followed by the first four bytes of its double
+// SHA-256, as in bitcoin addresses and WIF keys
+pub fn encode_check(payload: &[u8]) -> Result<Vec<u8>> {
+    Ok(encode(&[payload, &checksum(payload)?].concat()))
+}
+
+pub fn decode_check(buffer: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = decode(buffer)?;
+    if decoded.len() < CHECKSUM_LEN {
+        return Err(error("buffer is too short for a checksum").into());
+    }
+
+    let sum = decoded.split_off(decoded.len() - CHECKSUM_LEN);
+    if sum != checksum(&decoded)? {
+        return Err(error("checksum doesn't match").into());
+    }
+    Ok(decoded)
+}
+
+#[test]
+fn test_base58() {
+    assert_eq!(encode(b"Hello World!"), b"2NEpo7TZRRrLZSi2U".to_vec());
+    assert_eq!(
+        decode(b"2NEpo7TZRRrLZSi2U").unwrap(),
+        b"Hello World!".to_vec()
+    );
+    assert_eq!(
+        encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]),
+        b"11233QC4".to_vec()
+    );
+    assert_eq!(
+        decode(b"11233QC4").unwrap(),
+        vec![0, 0, 0x28, 0x7f, 0xb4, 0xcd]
+    );
+    assert_eq!(encode(b""), vec![]);
+    assert!(decode(b"0OIl").is_err());
+
+    // the genesis block's coinbase address: version byte 0 and the hash160
+    let address = b"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+    let payload = decode_check(address).unwrap();
+    assert_eq!(payload.len(), 21);
+    assert_eq!(payload[0], 0);
+    assert_eq!(encode_check(&payload).unwrap(), address.to_vec());
+
+    let mut tampered = address.to_vec();
+    tampered[5] = b'Q';
+    assert!(decode_check(&tampered).is_err());
+}
@@ -1,147 +1,399 @@
-use crate::utils::{DecodeError, DecodeType};
-use std::cmp::Ordering;
+use crate::alphabet::{decode_table, INVALID};
+use crate::error::{invalid_data, DecodeError, DecodeType, Result};
+use std::io::{self, Read, Write};
 
-const BASE64_CHAR_INDEXES: [char; 64] = [
-    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
-    'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l',
-    'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4',
-    '5', '6', '7', '8', '9', '+', '/',
-];
+const STANDARD_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
 
-enum Base64DecodeState {
-    A,
-    B,
-    C,
-    D,
+const STANDARD_TABLE: [u8; 256] = decode_table(STANDARD_CHARS);
+const URL_SAFE_TABLE: [u8; 256] = decode_table(URL_SAFE_CHARS);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    Standard,
+    // '-' and '_' for '+' and '/', per RFC 4648 section 5
+    UrlSafe,
 }
 
-impl Base64DecodeState {
-    fn next(self) -> Base64DecodeState {
+impl Alphabet {
+    fn chars(self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => STANDARD_CHARS,
+            Alphabet::UrlSafe => URL_SAFE_CHARS,
+        }
+    }
+
+    fn table(self) -> &'static [u8; 256] {
         match self {
-            Self::A => Self::B,
-            Self::B => Self::C,
-            Self::C => Self::D,
-            Self::D => Self::A,
+            Alphabet::Standard => &STANDARD_TABLE,
+            Alphabet::UrlSafe => &URL_SAFE_TABLE,
         }
     }
 }
 
-pub fn encode(buffer: &Vec<u8>) -> Vec<u8> {
-    use Base64DecodeState::*;
+// whether '=' padding has to be there, may be there, or mustn't be. encoding
+// pads unless it's forbidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    Required,
+    Optional,
+    Forbidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base64Config {
+    alphabet: Alphabet,
+    padding: Padding,
+    line_wrap: Option<usize>,
+    ignore_whitespace: bool,
+    strict: bool,
+}
 
-    let mut encoded_size = (buffer.len() / 3) * 4;
-    if buffer.len() % 3 != 0 {
-        encoded_size += 4;
+impl Base64Config {
+    pub const STANDARD: Base64Config = Base64Config {
+        alphabet: Alphabet::Standard,
+        padding: Padding::Required,
+        line_wrap: None,
+        ignore_whitespace: false,
+        strict: false,
+    };
+    pub const URL_SAFE: Base64Config = Base64Config {
+        alphabet: Alphabet::UrlSafe,
+        padding: Padding::Optional,
+        ..Base64Config::STANDARD
+    };
+    // RFC 2045, lines of 76
+    pub const MIME: Base64Config = Base64Config {
+        line_wrap: Some(76),
+        ignore_whitespace: true,
+        ..Base64Config::STANDARD
+    };
+    // RFC 7468, lines of 64
+    pub const PEM: Base64Config = Base64Config {
+        line_wrap: Some(64),
+        ignore_whitespace: true,
+        ..Base64Config::STANDARD
+    };
+
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Base64Config {
+        self.alphabet = alphabet;
+        self
     }
 
-    let mut encoded = vec![0u8; encoded_size];
+    pub fn padding(mut self, padding: Padding) -> Base64Config {
+        self.padding = padding;
+        self
+    }
 
-    let mut state = Base64DecodeState::A;
+    // encoded output is broken into lines of this many characters
+    pub fn line_wrap(mut self, line_wrap: Option<usize>) -> Base64Config {
+        self.line_wrap = line_wrap.filter(|width| *width > 0);
+        self
+    }
 
-    let mut byte_index = 0;
-    let mut encoded_index = 0;
+    // decoding skips ascii whitespace, line breaks included
+    pub fn ignore_whitespace(mut self, ignore_whitespace: bool) -> Base64Config {
+        self.ignore_whitespace = ignore_whitespace;
+        self
+    }
 
-    loop {
-        let curr_byte = buffer[byte_index];
-        let next_byte = if byte_index + 1 < buffer.len() {
-            buffer[byte_index + 1]
-        } else {
-            0
-        };
+    // decoding refuses a last character with bits set that no byte uses, so
+    // every byte string has exactly one encoding that decodes
+    pub fn strict(mut self, strict: bool) -> Base64Config {
+        self.strict = strict;
+        self
+    }
 
-        let char_index = match state {
-            A => (curr_byte >> 2) as usize,
-            B => {
-                byte_index += 1;
-                (((curr_byte & 0x03) << 4) | (next_byte >> 4)) as usize
+    pub fn encode(&self, buffer: &[u8]) -> Vec<u8> {
+        let chars = self.alphabet.chars();
+        let mut encoded = Vec::with_capacity(buffer.len().div_ceil(3) * 4);
+
+        for chunk in buffer.chunks(3) {
+            let bytes = [
+                chunk[0],
+                chunk.get(1).copied().unwrap_or(0),
+                chunk.get(2).copied().unwrap_or(0),
+            ];
+            let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+            for i in 0..=chunk.len() {
+                encoded.push(chars[(group >> (18 - 6 * i)) as usize & 0x3f]);
             }
-            C => {
-                byte_index += 1;
-                (((curr_byte & 0x0f) << 2) | (next_byte >> 6)) as usize
+            if self.padding != Padding::Forbidden {
+                encoded.resize(encoded.len() + 3 - chunk.len(), b'=');
+            }
+        }
+
+        match self.line_wrap {
+            Some(width) => encoded.chunks(width).collect::<Vec<_>>().join(&b'\n'),
+            None => encoded,
+        }
+    }
+
+    pub fn decode(&self, buffer: &[u8]) -> Result<Vec<u8>> {
+        let error = |msg: &str| DecodeError::new(DecodeType::Base64, msg).into();
+
+        let mut input = buffer.to_vec();
+        if self.ignore_whitespace {
+            input.retain(|byte| !byte.is_ascii_whitespace());
+        }
+
+        let padded_by = input.iter().rev().take_while(|byte| **byte == b'=').count();
+        input.truncate(input.len() - padded_by);
+        match (self.padding, padded_by) {
+            (Padding::Forbidden, 1..) => return Err(error("buffer is padded")),
+            (Padding::Required, 0) if !input.len().is_multiple_of(4) => {
+                return Err(error("buffer length isn't a multiple of 4"))
             }
-            D => {
-                byte_index += 1;
-                (curr_byte & 0x3f) as usize
+            (_, 1..) if padded_by > 2 || !(input.len() + padded_by).is_multiple_of(4) => {
+                return Err(error("buffer length isn't a multiple of 4"))
             }
-        };
+            _ => {}
+        }
+        if input.len() % 4 == 1 {
+            return Err(error("buffer length leaves a lone character"));
+        }
 
-        encoded[encoded_index] = BASE64_CHAR_INDEXES[char_index] as u8;
-        encoded_index += 1;
+        let table = self.alphabet.table();
+        let mut decoded = Vec::with_capacity(input.len() / 4 * 3 + 2);
+        for chunk in input.chunks(4) {
+            let mut group = 0u32;
+            for (i, c) in chunk.iter().enumerate() {
+                let value = table[*c as usize];
+                if value == INVALID {
+                    return Err(error(&format!(
+                        "buffer contains invalid base64 characters: ({})",
+                        *c as char
+                    )));
+                }
+                group |= (value as u32) << (18 - 6 * i);
+            }
 
-        if matches!(
-            (&state, byte_index.cmp(&buffer.len())),
-            (B | C | D, Ordering::Equal)
-        ) {
-            break;
+            let bytes = group.to_be_bytes();
+            let len = chunk.len() - 1;
+            if self.strict && bytes[1 + len..].iter().any(|byte| *byte != 0) {
+                return Err(error("buffer has trailing bits set"));
+            }
+            decoded.extend_from_slice(&bytes[1..1 + len]);
         }
 
-        state = state.next();
+        Ok(decoded)
+    }
+}
 
-        // if buffer.data.len() % 3 == 2 {
-        // 	encoded.data[encoded_index] = b'=';
-        // 	encoded_index += 1;
-        // } else if buffer.data.len() % 3 == 1 {
-        // 	encoded.data[encoded_index] = b'=';
-        // 	encoded_index += 1;
-        // 	encoded.data[encoded_index] = b'=';
-        // 	encoded_index += 1;
-        // }
+impl Default for Base64Config {
+    fn default() -> Self {
+        Base64Config::STANDARD
     }
+}
 
-    if buffer.len() % 3 == 2 {
-        encoded[encoded_index] = b'=';
-    } else if buffer.len() % 3 == 1 {
-        encoded[encoded_index] = b'=';
-        encoded[encoded_index + 1] = b'=';
+// how much of the underlying reader a decoder takes at a time
+const READ_CHUNK: usize = 4096;
+
+// encodes everything written to it into inner. the last group can't be
+// written until it's known to be the last, so finish has to be called to
+// get it out, and inner back
+pub struct Base64Encoder<W: Write> {
+    inner: W,
+    config: Base64Config,
+    pending: Vec<u8>,
+    column: usize,
+}
+
+impl<W: Write> Base64Encoder<W> {
+    pub fn new(inner: W, config: Base64Config) -> Base64Encoder<W> {
+        Base64Encoder {
+            inner,
+            config,
+            pending: Vec::with_capacity(3),
+            column: 0,
+        }
     }
 
-    encoded
+    // line breaks go between characters, never after the last one, so the
+    // output matches Base64Config::encode however the input was split up
+    fn write_encoded(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let encoded = self.config.line_wrap(None).encode(bytes);
+        let mut wrapped = Vec::with_capacity(encoded.len() * 2);
+        for c in encoded {
+            if let Some(width) = self.config.line_wrap {
+                if self.column == width {
+                    wrapped.push(b'\n');
+                    self.column = 0;
+                }
+            }
+            wrapped.push(c);
+            self.column += 1;
+        }
+        self.inner.write_all(&wrapped)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let pending = std::mem::take(&mut self.pending);
+        self.write_encoded(&pending)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
 }
 
-fn base64_char_value(c: char) -> Result<u8, DecodeError> {
-    if let Some(index) = BASE64_CHAR_INDEXES.iter().position(|cc| *cc == c) {
-        Ok(index as u8)
-    } else {
-        Err(DecodeError::new(
-            DecodeType::Base64,
-            &format!("buffer contains invalid base64 characters: ({})", c),
-        ))
+impl<W: Write> Write for Base64Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let whole = self.pending.len() - self.pending.len() % 3;
+        let groups: Vec<u8> = self.pending.drain(..whole).collect();
+        self.write_encoded(&groups)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
-pub fn decode(buffer: &Vec<u8>) -> Result<Vec<u8>, DecodeError> {
-    let mut decoded_len = (buffer.len() / 4) * 3;
-    if buffer.len() > 0 && buffer[buffer.len() - 1] == b'=' {
-        decoded_len -= 1;
+// decodes whatever inner gives it, a chunk at a time. the errors are the
+// ones Base64Config::decode would give, as InvalidData
+pub struct Base64Decoder<R: Read> {
+    inner: R,
+    config: Base64Config,
+    pending: Vec<u8>,
+    decoded: Vec<u8>,
+    position: usize,
+    // a padded group has been decoded, so there mustn't be anything after it
+    padded: bool,
+    eof: bool,
+}
+
+impl<R: Read> Base64Decoder<R> {
+    pub fn new(inner: R, config: Base64Config) -> Base64Decoder<R> {
+        Base64Decoder {
+            inner,
+            config,
+            pending: vec![],
+            decoded: vec![],
+            position: 0,
+            padded: false,
+            eof: false,
+        }
     }
-    if buffer.len() > 1 && buffer[buffer.len() - 2] == b'=' {
-        decoded_len -= 1;
+
+    pub fn into_inner(self) -> R {
+        self.inner
     }
 
-    let mut decoded = vec![0u8; decoded_len];
-    for i in 0..decoded_len {
-        let char_offset = (i / 3) * 4;
-        match i % 3 {
-            0 => {
-                let a = buffer[char_offset];
-                let b = buffer[char_offset + 1];
-                decoded[i] =
-                    (base64_char_value(a as char)? << 2) | (base64_char_value(b as char)? >> 4);
-            }
-            1 => {
-                let a = buffer[char_offset + 1];
-                let b = buffer[char_offset + 2];
-                decoded[i] = ((base64_char_value(a as char)? & 0x0F) << 4)
-                    | (base64_char_value(b as char)? >> 2);
-            }
-            _ => {
-                let a = buffer[char_offset + 2];
-                let b = buffer[char_offset + 3];
-                decoded[i] =
-                    ((base64_char_value(a as char)? & 0x03) << 6) | base64_char_value(b as char)?;
+    // decodes as many whole groups as have come in, or everything that's
+    // left at the end of the input
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = vec![0u8; READ_CHUNK];
+        let read = self.inner.read(&mut chunk)?;
+        chunk.truncate(read);
+        if self.config.ignore_whitespace {
+            chunk.retain(|byte| !byte.is_ascii_whitespace());
+        }
+        if self.padded && !chunk.is_empty() {
+            let error = DecodeError::new(DecodeType::Base64, "buffer continues after padding");
+            return Err(invalid_data(error.into()));
+        }
+        self.pending.extend(chunk);
+
+        let take = if read == 0 {
+            self.eof = true;
+            self.pending.len()
+        } else {
+            self.pending.len() - self.pending.len() % 4
+        };
+        let groups: Vec<u8> = self.pending.drain(..take).collect();
+        self.padded = groups.last() == Some(&b'=');
+        if self.padded && !self.pending.is_empty() {
+            let error = DecodeError::new(DecodeType::Base64, "buffer continues after padding");
+            return Err(invalid_data(error.into()));
+        }
+        self.decoded = self.config.decode(&groups).map_err(invalid_data)?;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Base64Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            if self.eof {
+                return Ok(0);
             }
+            self.fill()?;
         }
+
+        let len = buf.len().min(self.decoded.len() - self.position);
+        buf[..len].copy_from_slice(&self.decoded[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+pub fn encode(buffer: &[u8]) -> Vec<u8> {
+    Base64Config::STANDARD.encode(buffer)
+}
+
+pub fn decode(buffer: &[u8]) -> Result<Vec<u8>> {
+    Base64Config::STANDARD.decode(buffer)
+}
+
+#[test]
+fn test_base64_config() {
+    let url = Base64Config::URL_SAFE;
+    assert_eq!(url.encode(&[0xfb, 0xff]), b"-_8=".to_vec());
+    assert_eq!(url.decode(b"-_8").unwrap(), vec![0xfb, 0xff]);
+    assert!(decode(b"-_8=").is_err());
+    assert!(decode(b"+/8").is_err());
+
+    let bare = Base64Config::STANDARD.padding(Padding::Forbidden);
+    assert_eq!(bare.encode(b"hi"), b"aGk".to_vec());
+    assert!(bare.decode(b"aGk=").is_err());
+    assert!(decode(b"aGk==").is_err());
+    assert!(decode(b"a").is_err());
+
+    let wrapped = Base64Config::STANDARD.line_wrap(Some(4)).encode(b"foobar!");
+    assert_eq!(wrapped, b"Zm9v\nYmFy\nIQ==".to_vec());
+    assert_eq!(Base64Config::MIME.decode(&wrapped).unwrap(), b"foobar!");
+    assert!(decode(&wrapped).is_err());
+
+    // the last character of "aGl=" has a bit set that "hi" doesn't need
+    assert_eq!(decode(b"aGl=").unwrap(), b"hi".to_vec());
+    assert!(Base64Config::STANDARD.strict(true).decode(b"aGl=").is_err());
+    assert_eq!(encode(&[]), vec![]);
+}
+
+#[test]
+fn test_base64_streams() {
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7) as u8).collect();
+
+    // written a few bytes at a time, so groups straddle the writes
+    let mut encoder = Base64Encoder::new(vec![], Base64Config::MIME);
+    for chunk in data.chunks(5) {
+        encoder.write_all(chunk).unwrap();
     }
+    let encoded = encoder.finish().unwrap();
+    assert_eq!(encoded, Base64Config::MIME.encode(&data));
+
+    let mut decoded = vec![];
+    Base64Decoder::new(&encoded[..], Base64Config::MIME)
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
 
-    Ok(decoded)
+    let empty = Base64Encoder::new(vec![], Base64Config::STANDARD);
+    assert!(empty.finish().unwrap().is_empty());
+    let mut decoded = vec![];
+    assert!(Base64Decoder::new(&b"aGk=aGk="[..], Base64Config::STANDARD)
+        .read_to_end(&mut decoded)
+        .is_err());
+    // the rest of the chunk after a padded group, short of a group itself
+    assert!(Base64Config::URL_SAFE.decode(b"aGk=aGk").is_err());
+    assert!(Base64Decoder::new(&b"aGk=aGk"[..], Base64Config::URL_SAFE)
+        .read_to_end(&mut decoded)
+        .is_err());
+    assert!(Base64Decoder::new(&b"aGk=\naGk"[..], Base64Config::MIME)
+        .read_to_end(&mut decoded)
+        .is_err());
 }
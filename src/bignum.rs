@@ -0,0 +1,492 @@
+use openssl::bn::{BigNum, BigNumContext, BigNumContextRef, MsbOption};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Div, Mul, Neg, Rem, Shl, Shr, Sub};
+
+// arbitrary precision signed integers, on top of OpenSSL's BIGNUM. OpenSSL
+// only fails these operations on allocation failure or division by zero, so
+// like the operators it provides, they panic rather than return errors
+
+thread_local! {
+    static CTX: RefCell<BigNumContext> = RefCell::new(BigNumContext::new().unwrap());
+}
+
+fn with_ctx<T>(f: impl FnOnce(&mut BigNumContextRef) -> T) -> T {
+    CTX.with(|ctx| f(&mut ctx.borrow_mut()))
+}
+
+pub struct BigInt(BigNum);
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt(BigNum::new().unwrap())
+    }
+
+    pub fn one() -> BigInt {
+        BigInt::from(1u32)
+    }
+
+    pub fn from_dec_str(s: &str) -> Option<BigInt> {
+        BigNum::from_dec_str(s).ok().map(BigInt)
+    }
+
+    pub fn from_hex_str(s: &str) -> Option<BigInt> {
+        BigNum::from_hex_str(s).ok().map(BigInt)
+    }
+
+    // big endian, always non-negative
+    pub fn from_bytes_be(bytes: &[u8]) -> BigInt {
+        BigInt(BigNum::from_slice(bytes).unwrap())
+    }
+
+    // big endian magnitude, dropping the sign
+    pub fn to_bytes_be(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.is_negative() || self.num_bits() > 64 {
+            return None;
+        }
+        let bytes = self.to_bytes_be();
+        let mut padded = [0u8; 8];
+        padded[(8 - bytes.len())..].copy_from_slice(&bytes);
+        Some(u64::from_be_bytes(padded))
+    }
+
+    pub fn to_dec_string(&self) -> String {
+        self.0.to_dec_str().unwrap().to_string()
+    }
+
+    // uniformly random in [0, bound)
+    pub fn random_below(bound: &BigInt) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        bound.0.rand_range(&mut n).unwrap();
+        BigInt(n)
+    }
+
+    // uniformly random in [low, high)
+    pub fn random_range(low: &BigInt, high: &BigInt) -> BigInt {
+        low + &BigInt::random_below(&(high - low))
+    }
+
+    pub fn random_bits(bits: usize) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        n.rand(bits as i32, MsbOption::MAYBE_ZERO, false).unwrap();
+        BigInt(n)
+    }
+
+    pub fn generate_prime(bits: usize) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        n.generate_prime(bits as i32, false, None, None).unwrap();
+        BigInt(n)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.num_bits() == 0
+    }
+
+    pub fn is_one(&self) -> bool {
+        *self == BigInt::one()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+
+    pub fn is_even(&self) -> bool {
+        !self.0.is_bit_set(0)
+    }
+
+    pub fn is_probably_prime(&self) -> bool {
+        with_ctx(|ctx| self.0.is_prime(64, ctx).unwrap())
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.0.num_bits() as usize
+    }
+
+    pub fn bit(&self, n: usize) -> bool {
+        self.0.is_bit_set(n as i32)
+    }
+
+    pub fn abs(&self) -> BigInt {
+        let mut n = self.clone();
+        n.0.set_negative(false);
+        n
+    }
+
+    pub fn pow(&self, exponent: u32) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        with_ctx(|ctx| n.exp(&self.0, &BigInt::from(exponent).0, ctx).unwrap());
+        BigInt(n)
+    }
+
+    // the least non-negative residue, unlike % which keeps the dividend's sign
+    pub fn modulo(&self, modulus: &BigInt) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        with_ctx(|ctx| n.nnmod(&self.0, &modulus.0, ctx).unwrap());
+        BigInt(n)
+    }
+
+    pub fn mod_pow(&self, exponent: &BigInt, modulus: &BigInt) -> BigInt {
+        if exponent.is_negative() {
+            let inverse = self.mod_inverse(modulus).unwrap();
+            return inverse.mod_pow(&-exponent, modulus);
+        }
+
+        let mut n = BigNum::new().unwrap();
+        let reduced = self.modulo(modulus);
+        with_ctx(|ctx| n.mod_exp(&reduced.0, &exponent.0, &modulus.0, ctx).unwrap());
+        BigInt(n)
+    }
+
+    pub fn mod_inverse(&self, modulus: &BigInt) -> Option<BigInt> {
+        let mut n = BigNum::new().unwrap();
+        let reduced = self.modulo(modulus);
+        with_ctx(|ctx| n.mod_inverse(&reduced.0, &modulus.0, ctx))
+            .ok()
+            .map(|_| BigInt(n))
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut n = BigNum::new().unwrap();
+        with_ctx(|ctx| n.gcd(&self.0, &other.0, ctx).unwrap());
+        BigInt(n)
+    }
+
+    // the Legendre symbol for an odd prime modulus: 1, -1 or 0
+    pub fn legendre(&self, p: &BigInt) -> i32 {
+        let exponent = (p - &BigInt::one()) >> 1;
+        let symbol = self.mod_pow(&exponent, p);
+        if symbol.is_zero() {
+            0
+        } else if symbol.is_one() {
+            1
+        } else {
+            -1
+        }
+    }
+
+    // a square root mod an odd prime by Tonelli-Shanks, if there is one
+    pub fn mod_sqrt(&self, p: &BigInt) -> Option<BigInt> {
+        let n = self.modulo(p);
+        if n.is_zero() {
+            return Some(n);
+        }
+        if n.legendre(p) != 1 {
+            return None;
+        }
+
+        let one = BigInt::one();
+        let mut q = p - &one;
+        let mut s = 0;
+        while q.is_even() {
+            q = &q >> 1;
+            s += 1;
+        }
+
+        let mut z = BigInt::from(2u32);
+        while z.legendre(p) != -1 {
+            z = &z + &one;
+        }
+
+        let mut m = s;
+        let mut c = z.mod_pow(&q, p);
+        let mut t = n.mod_pow(&q, p);
+        let mut r = n.mod_pow(&((&q + &one) >> 1), p);
+
+        while !t.is_one() {
+            let mut i = 0;
+            let mut t_squared = t.clone();
+            while !t_squared.is_one() {
+                t_squared = (&t_squared * &t_squared).modulo(p);
+                i += 1;
+            }
+
+            let b = c.mod_pow(&(&one << (m - i - 1)), p);
+            m = i;
+            c = (&b * &b).modulo(p);
+            t = (&t * &c).modulo(p);
+            r = (&r * &b).modulo(p);
+        }
+
+        Some(r)
+    }
+
+    // the integer square root, rounded down
+    pub fn sqrt(&self) -> BigInt {
+        if self.is_zero() {
+            return BigInt::zero();
+        }
+
+        let mut x = &BigInt::one() << self.num_bits().div_ceil(2);
+        loop {
+            let y = &(&x + &(self / &x)) >> 1;
+            if y >= x {
+                return x;
+            }
+            x = y;
+        }
+    }
+
+    // the distinct primes below bound that divide this, by trial division.
+    // everything divides zero, so it's given no factors at all
+    pub fn small_prime_factors(&self, bound: u64) -> Vec<u64> {
+        let mut n = self.abs();
+        let mut factors = vec![];
+        if n.is_zero() {
+            return factors;
+        }
+
+        for d in 2..bound {
+            let divisor = BigInt::from(d);
+            if !(&n % &divisor).is_zero() {
+                continue;
+            }
+            factors.push(d);
+            while (&n % &divisor).is_zero() {
+                n = &n / &divisor;
+            }
+        }
+
+        factors
+    }
+}
+
+// combines x = r mod m for pairwise coprime moduli into the x mod the product
+// of the moduli that satisfies all of them, returned with that product. None
+// when a modulus is zero or two of them share a factor
+pub fn crt(residues: &[(BigInt, BigInt)]) -> Option<(BigInt, BigInt)> {
+    if residues.iter().any(|(_, m)| m.is_zero()) {
+        return None;
+    }
+
+    let modulus = residues
+        .iter()
+        .fold(BigInt::one(), |product, (_, m)| &product * m);
+
+    let mut x = BigInt::zero();
+    for (r, m) in residues {
+        let rest = &modulus / m;
+        let inverse = rest.mod_inverse(m)?;
+        x = &x + &(&(r * &rest) * &inverse);
+    }
+
+    Some((x.modulo(&modulus), modulus))
+}
+
+impl Clone for BigInt {
+    fn clone(&self) -> BigInt {
+        BigInt(self.0.to_owned().unwrap())
+    }
+}
+
+impl From<u32> for BigInt {
+    fn from(n: u32) -> BigInt {
+        BigInt(BigNum::from_u32(n).unwrap())
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(n: u64) -> BigInt {
+        BigInt::from_bytes_be(&n.to_be_bytes())
+    }
+}
+
+impl From<usize> for BigInt {
+    fn from(n: usize) -> BigInt {
+        BigInt::from(n as u64)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> BigInt {
+        let magnitude = BigInt::from(n.unsigned_abs());
+        if n < 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_dec_string())
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_dec_string())
+    }
+}
+
+impl PartialEq for BigInt {
+    fn eq(&self, other: &BigInt) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for BigInt {}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Hash for BigInt {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.is_negative().hash(state);
+        self.to_bytes_be().hash(state);
+    }
+}
+
+macro_rules! impl_binary_op {
+    ($trait:ident, $method:ident) => {
+        impl<'a, 'b> $trait<&'b BigInt> for &'a BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: &BigInt) -> BigInt {
+                BigInt((&*self.0).$method(&*other.0))
+            }
+        }
+
+        impl<'a> $trait<&'a BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: &BigInt) -> BigInt {
+                (&self).$method(other)
+            }
+        }
+
+        impl<'a> $trait<BigInt> for &'a BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: BigInt) -> BigInt {
+                self.$method(&other)
+            }
+        }
+
+        impl $trait<BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: BigInt) -> BigInt {
+                (&self).$method(&other)
+            }
+        }
+    };
+}
+
+// division truncates towards zero, and the remainder takes the sign of the
+// dividend, as in C
+impl_binary_op!(Add, add);
+impl_binary_op!(Sub, sub);
+impl_binary_op!(Mul, mul);
+impl_binary_op!(Div, div);
+impl_binary_op!(Rem, rem);
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        let mut n = self.clone();
+        let negative = !n.is_negative() && !n.is_zero();
+        n.0.set_negative(negative);
+        n
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+impl Shl<usize> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, n: usize) -> BigInt {
+        BigInt(&*self.0 << n as i32)
+    }
+}
+
+impl Shr<usize> for &BigInt {
+    type Output = BigInt;
+
+    fn shr(self, n: usize) -> BigInt {
+        BigInt(&*self.0 >> n as i32)
+    }
+}
+
+impl Shl<usize> for BigInt {
+    type Output = BigInt;
+
+    fn shl(self, n: usize) -> BigInt {
+        &self << n
+    }
+}
+
+impl Shr<usize> for BigInt {
+    type Output = BigInt;
+
+    fn shr(self, n: usize) -> BigInt {
+        &self >> n
+    }
+}
+
+#[test]
+fn test_bigint_arithmetic() {
+    let p = BigInt::from_dec_str("233970423115425145524320034830162017933").unwrap();
+    let a = BigInt::from(-95051i64);
+
+    assert_eq!(a.modulo(&p) + BigInt::from(95051u32), p);
+    assert_eq!((&a * &a).to_u64(), Some(95051 * 95051));
+    assert_eq!(
+        BigInt::from(-7i64) / BigInt::from(2u32),
+        BigInt::from(-3i64)
+    );
+    assert_eq!(
+        BigInt::from(-7i64) % BigInt::from(2u32),
+        BigInt::from(-1i64)
+    );
+    assert_eq!(BigInt::from(10u32).pow(3), BigInt::from(1000u32));
+    assert_eq!(BigInt::from(1000001u32).sqrt(), BigInt::from(1000u32));
+
+    let x = BigInt::from(123456789u32);
+    let inverse = x.mod_inverse(&p).unwrap();
+    assert!((&x * &inverse).modulo(&p).is_one());
+
+    let root = (&x * &x).mod_sqrt(&p).unwrap();
+    assert!(root == x || root == &p - &x);
+
+    assert_eq!(BigInt::from(360u32).small_prime_factors(100), vec![2, 3, 5]);
+    assert!(BigInt::zero().small_prime_factors(100).is_empty());
+
+    let residues = [
+        (BigInt::from(2u32), BigInt::from(3u32)),
+        (BigInt::from(3u32), BigInt::from(5u32)),
+    ];
+    assert_eq!(
+        crt(&residues),
+        Some((BigInt::from(8u32), BigInt::from(15u32)))
+    );
+    let shared = [
+        (BigInt::from(1u32), BigInt::from(4u32)),
+        (BigInt::from(3u32), BigInt::from(6u32)),
+    ];
+    assert_eq!(crt(&shared), None);
+    assert_eq!(crt(&[(BigInt::one(), BigInt::zero())]), None);
+}
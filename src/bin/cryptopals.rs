@@ -0,0 +1,675 @@
+use cryptopals_rs::base64::Base64Config;
+use cryptopals_rs::challenges;
+use cryptopals_rs::cypher::{DecodeDetails, BLOCK_SIZE};
+use cryptopals_rs::{ascii85, base32, base58, base64, cypher, hex};
+use cryptopals_rs::{ByteBuffer, ByteBufferDisplayFormat};
+use openssl::symm::Mode;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "usage: cryptopals <command> [options] [file]
+
+commands:
+    hex encode|decode
+    base64 encode|decode
+    base32|base58|ascii85|z85 encode|decode
+    xor --key KEY
+    aes --mode ecb|cbc|ctr --key KEY [--iv IV] [--nonce N] [--decrypt] [--no-pad]
+    break sbxor [--lines] [--top N]
+    break rkxor [--top N]
+    detect-ecb
+    run [NUMBERS] [--set N]
+
+options:
+    --in raw|hex|base64      how to decode the input (default raw)
+    --out raw|string|decimal|hex|grid|grid-ascii
+                             how to write the output (default raw, or
+                             string for the breakers)
+    --lines                  treat each line of input as its own buffer
+    --top N                  show the best N candidates from the breakers,
+                             and for rkxor the best N key sizes
+    -i, --input FILE         read from FILE rather than stdin
+    -o, --output FILE        write to FILE rather than stdout
+    --set N                  only run challenges from set N
+    --no-pad                 don't add or strip PKCS#7 padding for ecb and
+                             cbc, so input must be whole blocks
+
+keys and ivs are taken literally, unless prefixed with hex: or base64:.
+input is read from the file named last on the command line, if any, and
+stdin otherwise. challenges to run are given as numbers or inclusive
+ranges like 1..15, and default to all of them";
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputFormat {
+    Raw,
+    Hex,
+    Base64,
+}
+
+enum OutputFormat {
+    Raw,
+    Display(ByteBufferDisplayFormat),
+}
+
+struct Options {
+    command: Vec<String>,
+    input: Option<String>,
+    output: Option<String>,
+    in_format: InputFormat,
+    out_format: Option<OutputFormat>,
+    key: Option<Vec<u8>>,
+    iv: Option<Vec<u8>>,
+    nonce: u64,
+    mode: Option<String>,
+    decrypt: bool,
+    no_pad: bool,
+    lines: bool,
+    top: usize,
+    set: Option<u32>,
+}
+
+fn parse_input_format(s: &str) -> Result<InputFormat, String> {
+    match s {
+        "raw" => Ok(InputFormat::Raw),
+        "hex" => Ok(InputFormat::Hex),
+        "base64" => Ok(InputFormat::Base64),
+        _ => Err(format!("unknown input format {}", s)),
+    }
+}
+
+fn parse_output_format(s: &str) -> Result<OutputFormat, String> {
+    match s {
+        "raw" => Ok(OutputFormat::Raw),
+        "string" => Ok(OutputFormat::Display(ByteBufferDisplayFormat::String)),
+        "decimal" => Ok(OutputFormat::Display(ByteBufferDisplayFormat::Decimal)),
+        "hex" => Ok(OutputFormat::Display(ByteBufferDisplayFormat::Hex)),
+        "grid" => Ok(OutputFormat::Display(ByteBufferDisplayFormat::Grid)),
+        "grid-ascii" => Ok(OutputFormat::Display(ByteBufferDisplayFormat::GridAscii)),
+        _ => Err(format!("unknown output format {}", s)),
+    }
+}
+
+// a key or iv, given literally or as hex: or base64:
+fn parse_bytes(s: &str) -> Result<Vec<u8>, String> {
+    if let Some(encoded) = s.strip_prefix("hex:") {
+        hex::decode(encoded.as_bytes()).map_err(|e| e.to_string())
+    } else if let Some(encoded) = s.strip_prefix("base64:") {
+        base64::decode(encoded.as_bytes()).map_err(|e| e.to_string())
+    } else {
+        Ok(s.as_bytes().to_vec())
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        command: vec![],
+        input: None,
+        output: None,
+        in_format: InputFormat::Raw,
+        out_format: None,
+        key: None,
+        iv: None,
+        nonce: 0,
+        mode: None,
+        decrypt: false,
+        no_pad: false,
+        lines: false,
+        top: 1,
+        set: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--in" => options.in_format = parse_input_format(value()?)?,
+            "--out" => options.out_format = Some(parse_output_format(value()?)?),
+            "-i" | "--input" => options.input = Some(value()?.clone()),
+            "-o" | "--output" => options.output = Some(value()?.clone()),
+            "--key" => options.key = Some(parse_bytes(value()?)?),
+            "--iv" => options.iv = Some(parse_bytes(value()?)?),
+            "--nonce" => {
+                options.nonce = value()?
+                    .parse()
+                    .map_err(|_| String::from("--nonce must be a number"))?
+            }
+            "--mode" => options.mode = Some(value()?.clone()),
+            "-d" | "--decrypt" => options.decrypt = true,
+            "--no-pad" => options.no_pad = true,
+            "--lines" => options.lines = true,
+            "--top" => {
+                options.top = value()?
+                    .parse()
+                    .ok()
+                    .filter(|top| *top > 0)
+                    .ok_or_else(|| String::from("--top must be a positive number"))?
+            }
+            "--set" => {
+                options.set = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| String::from("--set must be a number"))?,
+                )
+            }
+            "-h" | "--help" => return Err(String::from(USAGE)),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option {}", arg))
+            }
+            _ => options.command.push(arg.clone()),
+        }
+    }
+
+    Ok(options)
+}
+
+fn read_input(options: &Options, path: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut input = vec![];
+    match path.or(options.input.as_deref()) {
+        Some(path) if path != "-" => {
+            input = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        }
+        _ => {
+            io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|e| format!("couldn't read stdin: {}", e))?;
+        }
+    }
+    Ok(input)
+}
+
+// what the text encodings are given on stdin usually ends in a newline
+fn strip_whitespace(input: &[u8]) -> Vec<u8> {
+    let mut stripped = input.to_vec();
+    stripped.retain(|byte| !byte.is_ascii_whitespace());
+    stripped
+}
+
+fn decode_input(input: &[u8], format: InputFormat) -> Result<Vec<u8>, String> {
+    match format {
+        InputFormat::Raw => Ok(input.to_vec()),
+        InputFormat::Hex => hex::decode_loose(input).map_err(|e| e.to_string()),
+        InputFormat::Base64 => Base64Config::STANDARD
+            .ignore_whitespace(true)
+            .decode(input)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+// the input as one buffer, or one per line with --lines
+fn input_buffers(options: &Options, path: Option<&str>) -> Result<Vec<Vec<u8>>, String> {
+    let input = read_input(options, path)?;
+
+    if options.lines {
+        input
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| decode_input(line, options.in_format))
+            .collect()
+    } else {
+        Ok(vec![decode_input(&input, options.in_format)?])
+    }
+}
+
+fn format_output(buffer: &[u8], format: &OutputFormat) -> Vec<u8> {
+    match format {
+        OutputFormat::Raw => buffer.to_vec(),
+        OutputFormat::Display(format) => {
+            let mut s = buffer.to_vec().to_string(*format);
+            if !s.ends_with('\n') {
+                s.push('\n');
+            }
+            s.into_bytes()
+        }
+    }
+}
+
+fn write_output(options: &Options, output: &[u8]) -> Result<(), String> {
+    match &options.output {
+        Some(path) if path != "-" => {
+            fs::write(path, output).map_err(|e| format!("couldn't write {}: {}", path, e))
+        }
+        _ => io::stdout()
+            .write_all(output)
+            .map_err(|e| format!("couldn't write stdout: {}", e)),
+    }
+}
+
+fn transform(
+    options: &Options,
+    path: Option<&str>,
+    f: impl Fn(&[u8]) -> Result<Vec<u8>, String>,
+) -> Result<(), String> {
+    let format = options.out_format.as_ref().unwrap_or(&OutputFormat::Raw);
+
+    let mut output = vec![];
+    for buffer in input_buffers(options, path)? {
+        let transformed = f(&buffer)?;
+        output.extend(format_output(&transformed, format));
+        if options.lines && matches!(format, OutputFormat::Raw) {
+            output.push(b'\n');
+        }
+    }
+
+    write_output(options, &output)
+}
+
+fn required<'a, T>(value: &'a Option<T>, name: &str) -> Result<&'a T, String> {
+    value
+        .as_ref()
+        .ok_or_else(|| format!("{} is required", name))
+}
+
+fn aes(options: &Options, input: &[u8]) -> Result<Vec<u8>, String> {
+    let key = required(&options.key, "--key")?;
+    if key.len() != BLOCK_SIZE {
+        return Err(format!("--key must be {} bytes", BLOCK_SIZE));
+    }
+    let mode = if options.decrypt {
+        Mode::Decrypt
+    } else {
+        Mode::Encrypt
+    };
+    let padded = options.mode.as_deref() != Some("ctr") && !options.no_pad;
+    if options.mode.as_deref() != Some("ctr")
+        && (options.decrypt || options.no_pad)
+        && !input.len().is_multiple_of(BLOCK_SIZE)
+    {
+        return Err(format!("input must be a multiple of {} bytes", BLOCK_SIZE));
+    }
+    let input = if padded && !options.decrypt {
+        cypher::pkcs7_pad(input, BLOCK_SIZE)
+    } else {
+        input.to_vec()
+    };
+
+    let output = match required(&options.mode, "--mode")?.as_str() {
+        "ecb" => cypher::aes_ecb(&input, key, mode),
+        "cbc" => {
+            let iv = required(&options.iv, "--iv")?;
+            if iv.len() != BLOCK_SIZE {
+                return Err(format!("--iv must be {} bytes", BLOCK_SIZE));
+            }
+            cypher::aes_cbc(&input, key, iv, mode)
+        }
+        "ctr" => cypher::aes_ctr(&input, key, options.nonce),
+        other => return Err(format!("unknown mode {}", other)),
+    };
+    let output = output.map_err(|e| e.to_string())?;
+
+    if padded && options.decrypt {
+        return cypher::pkcs7_unpad(output, BLOCK_SIZE)
+            .map_err(|_| String::from("bad padding, use --no-pad if the plaintext wasn't padded"));
+    }
+    Ok(output)
+}
+
+// the candidates best first, each with the line it came from if there's
+// more than one line. ranks are only worth printing when there's a choice
+fn report_break(
+    options: &Options,
+    ranked: &[(Option<usize>, DecodeDetails)],
+    mut report: Vec<u8>,
+) -> Result<(), String> {
+    let format = options
+        .out_format
+        .as_ref()
+        .unwrap_or(&OutputFormat::Display(ByteBufferDisplayFormat::String));
+
+    for (rank, (line, details)) in ranked.iter().enumerate() {
+        if options.top > 1 {
+            report.extend(format!("rank\t{}\n", rank + 1).into_bytes());
+        }
+        if let Some(line) = line {
+            report.extend(format!("line\t{}\n", line + 1).into_bytes());
+        }
+        report.extend(b"key\t");
+        report.extend(format_output(&details.key_buffer, format));
+        report.extend(format!("score\t{}\n", details.score).into_bytes());
+        report.extend(b"plaintext\n");
+        report.extend(format_output(&details.plaintext_buffer, format));
+        if rank + 1 < ranked.len() {
+            report.push(b'\n');
+        }
+    }
+
+    write_output(options, &report)
+}
+
+fn break_sb_xor(options: &Options, path: Option<&str>) -> Result<(), String> {
+    // the most english looking decryptions of any line. lines with nothing
+    // countable in them have no candidates at all
+    let ranked: Vec<_> = cypher::rank_sb_xor_lines(&input_buffers(options, path)?, options.top)
+        .into_iter()
+        .map(|(line, details)| (Some(line).filter(|_| options.lines), details))
+        .collect();
+    if ranked.is_empty() {
+        return Err(String::from("nothing decoded as english"));
+    }
+
+    report_break(options, &ranked, vec![])
+}
+
+fn break_rk_xor(options: &Options, path: Option<&str>) -> Result<(), String> {
+    let input = input_buffers(options, path)?.concat();
+    if input.len() < cypher::MIN_RK_XOR_LEN {
+        return Err(String::from("input is too short to guess a key size"));
+    }
+
+    let ranked: Vec<_> = cypher::rank_rk_xor(&input, cypher::MAX_KEYSIZE, options.top)
+        .into_iter()
+        .map(|details| (None, details))
+        .collect();
+    if ranked.is_empty() {
+        return Err(String::from("nothing decoded as english"));
+    }
+
+    // the key sizes that look likeliest before any breaking, for when the
+    // best candidates aren't obviously right
+    let mut report = vec![];
+    if options.top > 1 {
+        report.extend(b"keysize\tdistance\n");
+        for size in cypher::rank_keysizes(&input, options.top) {
+            report.extend(format!("{}\t{:.4}\n", size.keysize, size.score).into_bytes());
+        }
+        report.push(b'\n');
+    }
+
+    report_break(options, &ranked, report)
+}
+
+fn detect_ecb(options: &Options, path: Option<&str>) -> Result<(), String> {
+    let buffers = input_buffers(options, path)?;
+
+    let (line, dupes) = buffers
+        .iter()
+        .map(|buffer| buffer.dupe_blocks(BLOCK_SIZE))
+        .enumerate()
+        .max_by_key(|(_, dupes)| *dupes)
+        .ok_or_else(|| String::from("no input"))?;
+
+    let mut report = format!("line\t{}\nduplicate blocks\t{}\n", line + 1, dupes).into_bytes();
+    if dupes > 0 {
+        let format = options
+            .out_format
+            .as_ref()
+            .unwrap_or(&OutputFormat::Display(ByteBufferDisplayFormat::Grid));
+        report.extend(format_output(&buffers[line], format));
+    } else {
+        report.extend(b"no duplicate blocks, so probably not ECB\n");
+    }
+
+    write_output(options, &report)
+}
+
+// a challenge number, or an inclusive range of them
+fn parse_selector(selector: &str) -> Result<(u32, u32), String> {
+    let parse = |n: &str| {
+        n.parse::<u32>()
+            .map_err(|_| format!("bad challenge number {}", n))
+    };
+
+    match selector.split_once("..") {
+        Some((start, end)) => Ok((parse(start)?, parse(end.trim_start_matches('='))?)),
+        None => Ok((parse(selector)?, parse(selector)?)),
+    }
+}
+
+fn run_challenges(options: &Options, selectors: &[&str]) -> Result<(), String> {
+    let ranges = selectors
+        .iter()
+        .map(|selector| parse_selector(selector))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let selected: Vec<_> = challenges::registry()
+        .into_iter()
+        .filter(|challenge| {
+            ranges.is_empty()
+                || ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&challenge.number()))
+        })
+        .filter(|challenge| options.set.is_none_or(|set| challenge.set() == set))
+        .collect();
+    if selected.is_empty() {
+        return Err(String::from("no challenges selected"));
+    }
+
+    let mut failed = 0;
+    for challenge in selected.iter() {
+        let result = challenge.run();
+        let status = match &result {
+            Ok(output) => format!("{}\nok", output),
+            Err(e) => {
+                failed += 1;
+                format!("failed: {}", e)
+            }
+        };
+        let report = format!(
+            "challenge {} (set {}): {}\n{}\n\n",
+            challenge.number(),
+            challenge.set(),
+            challenge.title(),
+            status.trim_start_matches('\n')
+        );
+        write_output(options, report.as_bytes())?;
+    }
+
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} challenges failed",
+            failed,
+            selected.len()
+        ));
+    }
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_args(args)?;
+    let words: Vec<&str> = options.command.iter().map(String::as_str).collect();
+
+    if let ["run", selectors @ ..] = words.as_slice() {
+        return run_challenges(&options, selectors);
+    }
+
+    // everything after the command's own words is the input file, if any
+    let (command, path) = match words.as_slice() {
+        ["hex" | "base64" | "base32" | "base58" | "ascii85" | "z85" | "break", subcommand, rest @ ..] => {
+            (vec![words[0], *subcommand], rest)
+        }
+        [command, rest @ ..] => (vec![*command], rest),
+        [] => return Err(String::from(USAGE)),
+    };
+    if path.len() > 1 {
+        return Err(format!("unexpected argument {}", path[1]));
+    }
+    let path = path.first().copied();
+
+    match command.as_slice() {
+        ["hex", "encode"] => transform(&options, path, |input| Ok(hex::encode(input))),
+        ["hex", "decode"] => transform(&options, path, |input| {
+            decode_input(input, InputFormat::Hex)
+        }),
+        ["base64", "encode"] => transform(&options, path, |input| Ok(base64::encode(input))),
+        ["base64", "decode"] => transform(&options, path, |input| {
+            decode_input(input, InputFormat::Base64)
+        }),
+        ["base32", "encode"] => transform(&options, path, |input| Ok(base32::encode(input))),
+        ["base32", "decode"] => transform(&options, path, |input| {
+            base32::decode(&strip_whitespace(input)).map_err(|e| e.to_string())
+        }),
+        ["base58", "encode"] => transform(&options, path, |input| Ok(base58::encode(input))),
+        ["base58", "decode"] => transform(&options, path, |input| {
+            base58::decode(&strip_whitespace(input)).map_err(|e| e.to_string())
+        }),
+        ["ascii85", "encode"] => transform(&options, path, |input| Ok(ascii85::encode(input))),
+        ["ascii85", "decode"] => transform(&options, path, |input| {
+            ascii85::decode(input).map_err(|e| e.to_string())
+        }),
+        ["z85", "encode"] => transform(&options, path, |input| {
+            ascii85::encode_z85(input).map_err(|e| e.to_string())
+        }),
+        ["z85", "decode"] => transform(&options, path, |input| {
+            ascii85::decode_z85(&strip_whitespace(input)).map_err(|e| e.to_string())
+        }),
+        ["xor"] => {
+            let key = required(&options.key, "--key")?;
+            if key.is_empty() {
+                return Err(String::from("--key can't be empty"));
+            }
+            transform(&options, path, |input| {
+                let mut output = input.to_vec();
+                output.xor_with(key);
+                Ok(output)
+            })
+        }
+        ["aes"] => transform(&options, path, |input| aes(&options, input)),
+        ["break", "sbxor"] => break_sb_xor(&options, path),
+        ["break", "rkxor"] => break_rk_xor(&options, path),
+        ["detect-ecb"] => detect_ecb(&options, path),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+// runs a command line with the input and output going through temporary
+// files, returning the output
+#[cfg(test)]
+fn run_with_input(line: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+    let dir = env::temp_dir();
+    let id = format!("{}-{:x}", process::id(), rand::random::<u64>());
+    let in_path = dir.join(format!("cryptopals-in-{}", id));
+    let out_path = dir.join(format!("cryptopals-out-{}", id));
+    fs::write(&in_path, input).unwrap();
+
+    let mut line = args(line);
+    line.extend([
+        String::from("-o"),
+        out_path.to_string_lossy().into_owned(),
+        in_path.to_string_lossy().into_owned(),
+    ]);
+    let result = run(&line).map(|_| fs::read(&out_path).unwrap());
+
+    let _ = fs::remove_file(&in_path);
+    let _ = fs::remove_file(&out_path);
+    result
+}
+
+#[test]
+fn test_parse_args() {
+    let options = parse_args(&args(
+        "aes --mode cbc --key hex:000102030405060708090a0b0c0d0e0f --iv base64:AAAAAAAAAAAAAAAAAAAAAA== \
+         -d --no-pad --in hex --out grid --top 3 --set 2 file.bin",
+    ))
+    .unwrap();
+    assert_eq!(options.command, vec!["aes", "file.bin"]);
+    assert_eq!(options.key, Some((0..16).collect()));
+    assert_eq!(options.iv, Some(vec![0; 16]));
+    assert_eq!(options.mode.as_deref(), Some("cbc"));
+    assert!(options.decrypt && options.no_pad && !options.lines);
+    assert!(options.in_format == InputFormat::Hex);
+    assert!(matches!(
+        options.out_format,
+        Some(OutputFormat::Display(ByteBufferDisplayFormat::Grid))
+    ));
+    assert_eq!((options.top, options.set), (3, Some(2)));
+
+    assert_eq!(
+        parse_args(&args("--key")).err().unwrap(),
+        "--key needs a value"
+    );
+    assert!(parse_args(&args("--top 0")).is_err());
+    assert!(parse_args(&args("--nonce x")).is_err());
+    assert!(parse_args(&args("--in base32")).is_err());
+    assert!(parse_args(&args("--bogus")).is_err());
+    assert_eq!(parse_args(&args("hex encode -")).unwrap().command.len(), 3);
+}
+
+#[test]
+fn test_parse_selector() {
+    assert_eq!(parse_selector("7"), Ok((7, 7)));
+    assert_eq!(parse_selector("1..15"), Ok((1, 15)));
+    assert_eq!(parse_selector("1..=15"), Ok((1, 15)));
+    assert!(parse_selector("one").is_err());
+    assert!(parse_selector("1..").is_err());
+}
+
+#[test]
+fn test_commands() {
+    assert_eq!(run_with_input("hex encode", b"hi").unwrap(), b"6869");
+    assert_eq!(run_with_input("hex decode", b"68 69\n").unwrap(), b"hi");
+    assert_eq!(run_with_input("base64 encode", b"hi").unwrap(), b"aGk=");
+    assert_eq!(run_with_input("base64 decode", b"aGk=\n").unwrap(), b"hi");
+    assert_eq!(
+        run_with_input("base58 decode", b"Cn8eVZg\n").unwrap(),
+        b"hello"
+    );
+    assert_eq!(
+        run_with_input("xor --key ICE --lines", b"a\nb\n").unwrap(),
+        b"\x28\n\x2b\n"
+    );
+    assert!(run_with_input("xor", b"a").is_err());
+    assert!(run_with_input("hex", b"").is_err());
+    assert!(run_with_input("hex encode extra", b"").is_err());
+    assert_eq!(
+        run_with_input("break rkxor", b"abc").unwrap_err(),
+        "input is too short to guess a key size"
+    );
+
+    let lyrics = b"Play that funky music white boy!";
+    for mode in [
+        "ecb",
+        "cbc --iv hex:00000000000000000000000000000000",
+        "ctr --nonce 3",
+    ] {
+        let encrypt = format!("aes --mode {}", mode);
+        let mut line = args(&encrypt);
+        line.extend([String::from("--key"), String::from("YELLOW SUBMARINE")]);
+        let options = parse_args(&line).unwrap();
+        let cyphertext = aes(&options, lyrics).unwrap();
+        if mode == "ecb" {
+            // a whole block of padding, since the plaintext is aligned
+            assert_eq!(cyphertext.len(), lyrics.len() + BLOCK_SIZE);
+        }
+
+        line.push(String::from("--decrypt"));
+        let options = parse_args(&line).unwrap();
+        assert_eq!(aes(&options, &cyphertext).unwrap(), lyrics.to_vec());
+    }
+
+    // without padding, decrypting leaves the plaintext as it was, even when
+    // it ends in something that looks like padding
+    let block = b"YELLOW SUBMARIN\x01";
+    let mut line = args("aes --mode ecb --no-pad --key YELLOW_SUBMARINE");
+    let options = parse_args(&line).unwrap();
+    let cyphertext = aes(&options, block).unwrap();
+    assert_eq!(cyphertext.len(), BLOCK_SIZE);
+    assert!(aes(&options, b"YELLOW").is_err());
+    line.push(String::from("--decrypt"));
+    assert_eq!(
+        aes(&parse_args(&line).unwrap(), &cyphertext).unwrap(),
+        block
+    );
+
+    let cyphertext = aes(&options, &[b'A'; 16]).unwrap();
+    line.retain(|arg| arg != "--no-pad");
+    assert!(aes(&parse_args(&line).unwrap(), &cyphertext)
+        .unwrap_err()
+        .starts_with("bad padding"));
+}
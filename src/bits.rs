@@ -0,0 +1,212 @@
+use crate::error::{Error, Result};
+
+// bits are numbered from the most significant bit of the first byte, the
+// way they'd be read off a hex dump
+
+fn word(chunk: &[u8]) -> u64 {
+    u64::from_ne_bytes(chunk.try_into().unwrap_or([0; 8]))
+}
+
+// counts set bits eight bytes at a time, then mops up the rest
+pub fn popcount(bytes: &[u8]) -> usize {
+    let chunks = bytes.chunks_exact(8);
+    let rest = chunks.remainder();
+
+    let words: usize = chunks.map(|chunk| word(chunk).count_ones() as usize).sum();
+    words
+        + rest
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum::<usize>()
+}
+
+// the number of bits that differ between a and b, which must be the same
+// length
+pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<usize> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch(a.len(), b.len()));
+    }
+
+    let a_chunks = a.chunks_exact(8);
+    let b_chunks = b.chunks_exact(8);
+    let rest: usize = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| (x ^ y).count_ones() as usize)
+        .sum();
+
+    let words: usize = a_chunks
+        .zip(b_chunks)
+        .map(|(x, y)| (word(x) ^ word(y)).count_ones() as usize)
+        .sum();
+    Ok(words + rest)
+}
+
+fn bit_position(bytes: &[u8], index: usize) -> Result<(usize, u8)> {
+    if index >= bytes.len() * 8 {
+        return Err(Error::OutOfRange(index));
+    }
+    Ok((index / 8, 0x80 >> (index % 8)))
+}
+
+pub fn get_bit(bytes: &[u8], index: usize) -> Result<bool> {
+    let (byte, mask) = bit_position(bytes, index)?;
+    Ok(bytes[byte] & mask != 0)
+}
+
+pub fn set_bit(bytes: &mut [u8], index: usize, value: bool) -> Result<()> {
+    let (byte, mask) = bit_position(bytes, index)?;
+    if value {
+        bytes[byte] |= mask;
+    } else {
+        bytes[byte] &= !mask;
+    }
+    Ok(())
+}
+
+pub fn flip_bit(bytes: &mut [u8], index: usize) -> Result<()> {
+    let (byte, mask) = bit_position(bytes, index)?;
+    bytes[byte] ^= mask;
+    Ok(())
+}
+
+// rotates the whole buffer as one long string of bits, so bits leaving the
+// front of the first byte come back in at the end of the last
+pub fn rotate_left(bytes: &[u8], n: usize) -> Vec<u8> {
+    if bytes.is_empty() {
+        return vec![];
+    }
+    let n = n % (bytes.len() * 8);
+    let (byte_shift, bit_shift) = (n / 8, n % 8);
+
+    (0..bytes.len())
+        .map(|i| {
+            let high = bytes[(i + byte_shift) % bytes.len()];
+            let low = bytes[(i + byte_shift + 1) % bytes.len()];
+            if bit_shift == 0 {
+                high
+            } else {
+                (high << bit_shift) | (low >> (8 - bit_shift))
+            }
+        })
+        .collect()
+}
+
+pub fn rotate_right(bytes: &[u8], n: usize) -> Vec<u8> {
+    if bytes.is_empty() {
+        return vec![];
+    }
+    let len = bytes.len() * 8;
+    rotate_left(bytes, len - n % len)
+}
+
+// splits buffer into rows of width bytes and hands back the columns, so
+// column i holds every byte a repeating key of that width xors with key[i]
+pub fn transpose(buffer: &[u8], width: usize) -> Vec<Vec<u8>> {
+    (0..width)
+        .map(|column| buffer.iter().skip(column).step_by(width).copied().collect())
+        .collect()
+}
+
+fn plane(bytes: &[u8], bit: usize) -> Vec<u8> {
+    bytes
+        .chunks(8)
+        .map(|chunk| {
+            chunk.iter().enumerate().fold(0u8, |plane, (i, byte)| {
+                plane | (((byte << bit) & 0x80) >> i)
+            })
+        })
+        .collect()
+}
+
+// bit slicing: gathers bit `bit` (0 being the most significant) of every
+// byte, packed eight to a byte in the same order
+pub fn bit_plane(bytes: &[u8], bit: usize) -> Result<Vec<u8>> {
+    if bit >= 8 {
+        return Err(Error::OutOfRange(bit));
+    }
+    Ok(plane(bytes, bit))
+}
+
+// the 8x8 bit matrix transpose of a block of eight bytes, where byte i of
+// the result is bit plane i of the block. it's its own inverse
+pub fn transpose_bits(block: [u8; 8]) -> [u8; 8] {
+    let mut transposed = [0u8; 8];
+    for (bit, row) in transposed.iter_mut().enumerate() {
+        *row = plane(&block, bit)[0];
+    }
+    transposed
+}
+
+// one line per byte that differs, with both bytes in binary and a ^ under
+// every bit that changed. bytes past the end of the shorter buffer are
+// listed against nothing
+pub fn diff(a: &[u8], b: &[u8]) -> String {
+    let mut s = String::new();
+
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) if x != y => {
+                let marks: String = (0..8)
+                    .map(|bit| {
+                        if (x ^ y) & (0x80 >> bit) != 0 {
+                            '^'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                s.push_str(&format!("{:07x} {:08b} {:08b} {}\n", i, x, y, marks));
+            }
+            (Some(x), None) => s.push_str(&format!("{:07x} {:08b} --------\n", i, x)),
+            (None, Some(y)) => s.push_str(&format!("{:07x} -------- {:08b}\n", i, y)),
+            _ => {}
+        }
+    }
+
+    s
+}
+
+#[test]
+fn test_hamming_distance() {
+    assert_eq!(
+        hamming_distance(b"this is a test", b"wokka wokka!!!").unwrap(),
+        37
+    );
+    assert_eq!(popcount(b"wokka wokka!!!"), 57);
+    assert!(matches!(
+        hamming_distance(b"this", b"wokka"),
+        Err(Error::LengthMismatch(4, 5))
+    ));
+}
+
+#[test]
+fn test_bit_helpers() {
+    let mut bytes = vec![0b1000_0001, 0b0100_0000];
+    assert!(get_bit(&bytes, 0).unwrap());
+    assert!(get_bit(&bytes, 9).unwrap());
+    assert!(get_bit(&bytes, 16).is_err());
+
+    flip_bit(&mut bytes, 7).unwrap();
+    set_bit(&mut bytes, 15, true).unwrap();
+    assert_eq!(bytes, vec![0b1000_0000, 0b0100_0001]);
+
+    assert_eq!(rotate_left(&bytes, 1), vec![0b0000_0000, 0b1000_0011]);
+    assert_eq!(rotate_right(&rotate_left(&bytes, 11), 11), bytes);
+
+    assert_eq!(bit_plane(b"abcdefgh", 7).unwrap(), vec![0b1010_1010]);
+    assert_eq!(bit_plane(b"", 0).unwrap(), vec![]);
+    assert!(matches!(bit_plane(b"a", 8), Err(Error::OutOfRange(8))));
+
+    let block = *b"abcdefgh";
+    assert_eq!(transpose_bits(transpose_bits(block)), block);
+    assert_eq!(transpose_bits(block)[1], 0xff);
+    assert_eq!(
+        transpose(b"abcdefg", 3),
+        vec![b"adg".to_vec(), b"be".to_vec(), b"cf".to_vec()]
+    );
+
+    assert_eq!(diff(b"ab", b"a"), "0000001 01100010 --------\n");
+    assert_eq!(diff(b"a", b"c"), "0000000 01100001 01100011 ......^.\n");
+}
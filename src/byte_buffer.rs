@@ -1,3 +1,5 @@
+use crate::bits;
+use crate::error::Result;
 use rand::distributions;
 use rand::prelude::*;
 
@@ -50,8 +52,9 @@ impl ByteBuffer for Vec<u8> {
     fn dupe_blocks(&self, blocksize: usize) -> usize {
         let mut dupe_blocks = 0;
 
-        for x in 0..((self.len() / blocksize) - 1) {
-            for y in (x + 1)..(self.len() / blocksize) {
+        let num_blocks = self.len() / blocksize;
+        for x in 0..num_blocks {
+            for y in (x + 1)..num_blocks {
                 let block_a = &self[(x * blocksize)..((x + 1) * blocksize)];
                 let block_b = &self[(y * blocksize)..((y + 1) * blocksize)];
                 if block_a.iter().zip(block_b).all(|(a, b)| *a == *b) {
@@ -148,7 +151,7 @@ impl ByteBuffer for Vec<u8> {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ByteBufferDisplayFormat {
     String,
     Decimal,
@@ -164,17 +167,6 @@ pub fn xor(a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
     result
 }
 
-pub fn distance(a: &Vec<u8>, b: &Vec<u8>) -> usize {
-    let len = if a.len() > b.len() { b.len() } else { a.len() };
-
-    let mut dist = 0usize;
-    for i in 0..len {
-        let mut xored_byte = a[i] & b[i];
-        while xored_byte != 0 {
-            dist += (xored_byte & 0x01) as usize;
-            xored_byte >>= 1;
-        }
-    }
-
-    dist
+pub fn distance(a: &[u8], b: &[u8]) -> Result<usize> {
+    bits::hamming_distance(a, b)
 }
@@ -0,0 +1,76 @@
+use crate::error::{Error, Result};
+use std::fmt;
+
+mod set1;
+mod set2;
+
+pub use set1::*;
+pub use set2::*;
+
+// what a challenge found: the answer on its own, for checking against the
+// known one, and a report for people
+pub struct Output {
+    pub answer: String,
+    pub report: String,
+}
+
+impl Output {
+    // for challenges whose answer is all there is to say
+    pub fn answer(answer: String) -> Output {
+        Output {
+            report: answer.clone(),
+            answer,
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.report)
+    }
+}
+
+pub trait Challenge {
+    fn number(&self) -> u32;
+    fn set(&self) -> u32;
+    fn title(&self) -> &'static str;
+    fn run(&self) -> Result<Output>;
+}
+
+// every challenge, in order
+pub fn registry() -> Vec<Box<dyn Challenge>> {
+    vec![
+        Box::new(Challenge1),
+        Box::new(Challenge2),
+        Box::new(Challenge3),
+        Box::new(Challenge4),
+        Box::new(Challenge5),
+        Box::new(Challenge6),
+        Box::new(Challenge7),
+        Box::new(Challenge8),
+        Box::new(Challenge9),
+        Box::new(Challenge10),
+        Box::new(Challenge11),
+        Box::new(Challenge12),
+        Box::new(Challenge13),
+        Box::new(Challenge14),
+        Box::new(Challenge15),
+    ]
+}
+
+pub fn find(number: u32) -> Option<Box<dyn Challenge>> {
+    registry()
+        .into_iter()
+        .find(|challenge| challenge.number() == number)
+}
+
+// checks a computed value against the one the challenge gives
+fn expect(expected: &str, got: &str) -> Result<()> {
+    if expected != got {
+        return Err(Error::Mismatch {
+            expected: expected.to_owned(),
+            got: got.to_owned(),
+        });
+    }
+    Ok(())
+}
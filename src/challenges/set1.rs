@@ -0,0 +1,282 @@
+use super::{expect, Challenge, Output};
+use crate::base64::{self, Base64Config};
+use crate::byte_buffer::{self, ByteBuffer, ByteBufferDisplayFormat};
+use crate::error::{Error, Result};
+use crate::{cypher, hex};
+use openssl::symm::Mode;
+
+const CHALLENGE_4_DATA: &str = include_str!("data/4.txt");
+const CHALLENGE_6_DATA: &str = include_str!("data/6.txt");
+const CHALLENGE_7_DATA: &str = include_str!("data/7.txt");
+const CHALLENGE_8_DATA: &str = include_str!("data/8.txt");
+
+fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    hex::decode(s.as_bytes())
+}
+
+// the data files are base64 wrapped over several lines
+pub(super) fn decode_base64_file(contents: &str) -> Result<Vec<u8>> {
+    Base64Config::STANDARD
+        .ignore_whitespace(true)
+        .decode(contents.as_bytes())
+}
+
+fn as_string(buffer: &Vec<u8>) -> String {
+    buffer.to_string(ByteBufferDisplayFormat::String)
+}
+
+pub struct Challenge1;
+
+impl Challenge for Challenge1 {
+    fn number(&self) -> u32 {
+        1
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Convert hex to base64"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let decoded = decode_hex("49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d")?;
+        let got = as_string(&base64::encode(&decoded));
+
+        expect(
+            "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t",
+            &got,
+        )?;
+        Ok(Output::answer(got))
+    }
+}
+
+pub struct Challenge2;
+
+impl Challenge for Challenge2 {
+    fn number(&self) -> u32 {
+        2
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Fixed XOR"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let buffer_a = decode_hex("1c0111001f010100061a024b53535009181c")?;
+        let buffer_b = decode_hex("686974207468652062756c6c277320657965")?;
+        let got = as_string(&hex::encode(&byte_buffer::xor(&buffer_a, &buffer_b)));
+
+        expect("746865206b696420646f6e277420706c6179", &got)?;
+        Ok(Output::answer(got))
+    }
+}
+
+pub struct Challenge3;
+
+impl Challenge for Challenge3 {
+    fn number(&self) -> u32 {
+        3
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Single-byte XOR cipher"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let cyphertext =
+            decode_hex("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736")?;
+
+        let details = cypher::decode_sb_xor(&cyphertext)?;
+        let key = details.key_buffer[0];
+        let plaintext = as_string(&details.plaintext_buffer);
+
+        Ok(Output {
+            report: format!(
+                "decoded using key {} ({})\nwith score {}\n{}",
+                key as char, key, details.score, plaintext
+            ),
+            answer: plaintext,
+        })
+    }
+}
+
+pub struct Challenge4;
+
+impl Challenge for Challenge4 {
+    fn number(&self) -> u32 {
+        4
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Detect single-character XOR"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let lines = CHALLENGE_4_DATA
+            .lines()
+            .map(decode_hex)
+            .collect::<Result<Vec<_>>>()?;
+
+        let (line, details) = cypher::rank_sb_xor_lines(&lines, 1)
+            .pop()
+            .ok_or(Error::NoCandidate("no line decodes as english"))?;
+        let plaintext = as_string(&details.plaintext_buffer);
+
+        Ok(Output {
+            report: format!(
+                "decoded using key {} ({})\nscore {}\nfrom\t{}\nto\t{}",
+                details.key_buffer[0] as char,
+                details.key_buffer[0],
+                details.score,
+                as_string(&hex::encode(&lines[line])),
+                plaintext
+            ),
+            answer: plaintext,
+        })
+    }
+}
+
+pub struct Challenge5;
+
+impl Challenge for Challenge5 {
+    fn number(&self) -> u32 {
+        5
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Implement repeating-key XOR"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let plaintext =
+            b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal".to_vec();
+        let key = b"ICE".to_vec();
+        let got = as_string(&hex::encode(&byte_buffer::xor(&plaintext, &key)));
+
+        expect("0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f", &got)?;
+        Ok(Output::answer(got))
+    }
+}
+
+pub struct Challenge6;
+
+impl Challenge for Challenge6 {
+    fn number(&self) -> u32 {
+        6
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Break repeating-key XOR"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let cyphertext = decode_base64_file(CHALLENGE_6_DATA)?;
+
+        let details = cypher::decode_rk_xor(&cyphertext)?;
+        let key = as_string(&details.key_buffer);
+
+        Ok(Output {
+            report: format!(
+                "decoded using key\n\"{}\"\nto\n\"{}\"",
+                key,
+                as_string(&details.plaintext_buffer)
+            ),
+            answer: key,
+        })
+    }
+}
+
+pub struct Challenge7;
+
+impl Challenge for Challenge7 {
+    fn number(&self) -> u32 {
+        7
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "AES in ECB mode"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let cyphertext = decode_base64_file(CHALLENGE_7_DATA)?;
+        let key = b"YELLOW SUBMARINE".to_vec();
+
+        let mut plaintext = cypher::aes_ecb(&cyphertext, &key, Mode::Decrypt)?;
+        if let Some(padded_by) = plaintext.is_padded_for_blocksize(16) {
+            plaintext.truncate(plaintext.len() - padded_by);
+        }
+
+        Ok(Output::answer(as_string(&plaintext)))
+    }
+}
+
+pub struct Challenge8;
+
+impl Challenge for Challenge8 {
+    fn number(&self) -> u32 {
+        8
+    }
+
+    fn set(&self) -> u32 {
+        1
+    }
+
+    fn title(&self) -> &'static str {
+        "Detect AES in ECB mode"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let mut most_dupes: Option<(usize, usize, Vec<u8>)> = None;
+
+        for (line_number, line) in CHALLENGE_8_DATA.lines().enumerate() {
+            let buffer = decode_hex(line)?;
+            let dupe_blocks = buffer.dupe_blocks(16);
+
+            if most_dupes
+                .as_ref()
+                .is_none_or(|(most, _, _)| *most < dupe_blocks)
+            {
+                most_dupes = Some((dupe_blocks, line_number + 1, buffer));
+            }
+        }
+
+        let (dupe_blocks, line_number, buffer) =
+            most_dupes.ok_or(Error::NoCandidate("no lines"))?;
+
+        Ok(Output {
+            report: format!(
+                "line {} has {} duplicate blocks\n{}",
+                line_number,
+                dupe_blocks,
+                buffer.to_string(ByteBufferDisplayFormat::Grid)
+            ),
+            answer: line_number.to_string(),
+        })
+    }
+}
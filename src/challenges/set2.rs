@@ -0,0 +1,375 @@
+use super::set1::decode_base64_file;
+use super::{expect, Challenge, Output};
+use crate::byte_buffer::{ByteBuffer, ByteBufferDisplayFormat};
+use crate::cut_and_paste::{self, Template};
+use crate::cypher::{self, BlockMode, OracleMode};
+use crate::error::{Error, Result};
+use crate::kv::{Duplicates, KvCodec, KvMap, Separators};
+use crate::session::{Profile, ProfileBuilder, Role};
+use crate::{base64, hex};
+use openssl::symm::Mode;
+use std::str;
+
+const CHALLENGE_10_DATA: &str = include_str!("data/10.txt");
+
+const UNKNOWN_CONTENT_ENCODED: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK";
+
+fn as_string(buffer: &Vec<u8>) -> String {
+    buffer.to_string(ByteBufferDisplayFormat::String)
+}
+
+pub struct Challenge9;
+
+impl Challenge for Challenge9 {
+    fn number(&self) -> u32 {
+        9
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "Implement PKCS#7 padding"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let mut buffer = b"YELLOW SUBMARINE".to_vec();
+        buffer.pad_for_blocksize(20);
+
+        let got = as_string(&hex::encode(&buffer));
+        expect("59454c4c4f57205355424d4152494e4504040404", &got)?;
+
+        Ok(Output {
+            report: buffer.to_string(ByteBufferDisplayFormat::Grid),
+            answer: got,
+        })
+    }
+}
+
+pub struct Challenge10;
+
+impl Challenge for Challenge10 {
+    fn number(&self) -> u32 {
+        10
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "Implement CBC mode"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let cyphertext = decode_base64_file(CHALLENGE_10_DATA)?;
+        let key = b"YELLOW SUBMARINE".to_vec();
+        let iv = vec![0u8; 16];
+
+        let mut plaintext = cypher::aes_cbc(&cyphertext, &key, &iv, Mode::Decrypt)?;
+        if let Some(padded_by) = plaintext.is_padded_for_blocksize(16) {
+            plaintext.truncate(plaintext.len() - padded_by);
+        }
+
+        Ok(Output::answer(as_string(&plaintext)))
+    }
+}
+
+pub struct Challenge11;
+
+impl Challenge for Challenge11 {
+    fn number(&self) -> u32 {
+        11
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "An ECB/CBC detection oracle"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let buffer = vec![b'X'; 49];
+        let (encrypted, block_mode) = cypher::encryption_oracle(&buffer)?;
+        let detected = match encrypted.dupe_blocks(16) {
+            0 => BlockMode::CBC,
+            _ => BlockMode::ECB,
+        };
+
+        expect(&format!("{:?}", block_mode), &format!("{:?}", detected))?;
+        Ok(Output::answer(format!("{:?}", detected)))
+    }
+}
+
+// how many bytes the oracle appends, from how much input it takes to make
+// its output grow by a block. ECB padding here adds nothing when the
+// plaintext is already block aligned, so the output grows as soon as the
+// plaintext passes a multiple of the block size
+fn appended_len(oracle: &dyn Fn(&Vec<u8>) -> Result<Vec<u8>>, prefix_len: usize) -> Result<usize> {
+    let base_len = oracle(&vec![])?.len();
+    let mut input_len = 1;
+    while oracle(&vec![b'X'; input_len])?.len() == base_len {
+        input_len += 1;
+    }
+    Ok(base_len + 1 - input_len - prefix_len)
+}
+
+// block i of the oracle's output for input
+fn oracle_block(
+    oracle: &dyn Fn(&Vec<u8>) -> Result<Vec<u8>>,
+    input: &Vec<u8>,
+    block: usize,
+) -> Result<Vec<u8>> {
+    oracle(input)?
+        .get(block * 16..(block + 1) * 16)
+        .map(<[u8]>::to_vec)
+        .ok_or(Error::OutOfRange(block))
+}
+
+// byte at a time ECB decryption. each unknown byte is lined up as the last
+// of a block, behind filler and the 15 bytes before it, and matched against
+// all 256 possible blocks. filler_len is how many bytes it takes to pad any
+// prefix out to a block boundary, and first_block the block after it
+fn decrypt_appended(
+    oracle: &dyn Fn(&Vec<u8>) -> Result<Vec<u8>>,
+    filler_len: usize,
+    first_block: usize,
+    unknown_len: usize,
+) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+
+    for byte_index in 0..unknown_len {
+        let target_block = first_block + byte_index / 16;
+
+        let prepend_with = vec![b'X'; filler_len + 15 - (byte_index % 16)];
+        let oracle_output = oracle_block(oracle, &prepend_with, target_block)?;
+
+        let decoded_chunk = if decoded.len() < 15 {
+            [&vec![b'X'; 15 - decoded.len()][..], &decoded[..]].concat()
+        } else {
+            decoded[(decoded.len() - 15)..].to_vec()
+        };
+
+        let mut prepend_known = [&vec![b'X'; filler_len][..], &decoded_chunk[..], &[0]].concat();
+        let last = prepend_known.len() - 1;
+        let mut byte = None;
+        for candidate in 0..=255u8 {
+            prepend_known[last] = candidate;
+            if oracle_block(oracle, &prepend_known, first_block)? == oracle_output {
+                byte = Some(candidate);
+                break;
+            }
+        }
+        decoded.push(byte.ok_or(Error::NoCandidate("no byte matches the oracle's block"))?);
+    }
+
+    Ok(decoded)
+}
+
+fn unknown_content() -> Result<Vec<u8>> {
+    base64::decode(UNKNOWN_CONTENT_ENCODED.as_bytes())
+}
+
+pub struct Challenge12;
+
+impl Challenge for Challenge12 {
+    fn number(&self) -> u32 {
+        12
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "Byte-at-a-time ECB decryption (Simple)"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let unknown_content = unknown_content()?;
+        let oracle = cypher::make_oracle(&unknown_content, OracleMode::Simple);
+
+        let unknown_len = appended_len(&oracle, 0)?;
+        let decoded = decrypt_appended(&oracle, 0, 0, unknown_len)?;
+
+        Ok(Output::answer(as_string(&decoded)))
+    }
+}
+
+// the challenge's own encoding, which strips '&' and '=' out of the email
+// rather than escaping them, and leaves the role last. session::Profile's
+// encoding escapes them, which is what keeps its ECB tokens from being cut
+// and pasted like this
+fn codec() -> KvCodec {
+    KvCodec::new(Separators::QUERY).duplicates(Duplicates::Error)
+}
+
+fn profile_for(email: &str) -> Result<String> {
+    let mut stripped = email.to_owned();
+    stripped.retain(|c| c != '=' && c != '&');
+
+    let mut map = KvMap::new();
+    map.push("email", stripped);
+    map.push("uid", "10");
+    map.push("role", Role::User.to_string());
+    codec().encode(&map)
+}
+
+// there's no expiry in the challenge's encoding, so these never expire
+fn profile_from(encoded: &str) -> Result<Profile> {
+    let map = codec().decode(encoded)?;
+    let field = |name: &str| {
+        map.get(name)
+            .ok_or_else(|| Error::Parse(format!("Missing field {}", name)))
+    };
+
+    ProfileBuilder::new()
+        .email(field("email")?)
+        .role(field("role")?.parse()?)
+        .uid(
+            field("uid")?
+                .parse()
+                .map_err(|_| Error::Parse(String::from("Invalid uid")))?,
+        )
+        .expires_at(u64::MAX)
+        .build()
+}
+
+type ProfileEncrypter = Box<dyn Fn(&str) -> Result<Vec<u8>>>;
+type ProfileDecrypter = Box<dyn Fn(&Vec<u8>) -> Result<Profile>>;
+
+fn make_profile_oracle() -> (ProfileEncrypter, ProfileDecrypter) {
+    let rand_key = Vec::<u8>::from_rand_bytes(16);
+    let rand_key_clone = rand_key.clone();
+
+    let encrypt = Box::new(move |email: &str| {
+        let encoded_profile = profile_for(email)?;
+        cypher::aes_ecb(encoded_profile.as_bytes(), &rand_key, Mode::Encrypt)
+    });
+
+    let decrypt = Box::new(move |encrypted: &Vec<u8>| {
+        let mut decrypted = cypher::aes_ecb(encrypted, &rand_key_clone, Mode::Decrypt)?;
+        if let Some(padded_by) = decrypted.is_padded_for_blocksize(16) {
+            decrypted.truncate(decrypted.len() - padded_by);
+        }
+
+        match str::from_utf8(&decrypted) {
+            Err(_) => Err(Error::Parse(String::from("Invalid utf8"))),
+            Ok(decrypted_str) => profile_from(decrypted_str),
+        }
+    });
+
+    (encrypt, decrypt)
+}
+
+pub struct Challenge13;
+
+impl Challenge for Challenge13 {
+    fn number(&self) -> u32 {
+        13
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "ECB cut-and-paste"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let (encrypt_prof, decrypt_prof) = make_profile_oracle();
+
+        let oracle = |input: &[u8]| encrypt_prof(&String::from_utf8_lossy(input));
+
+        // the email is as long as it takes to end the second block with
+        // "&role="
+        let template = Template::new(b"email=", b"&uid=10&role=user").forbidding(b"=&");
+        let target = b"email=foooo@bar.com&uid=10&role=admin";
+        let pasted = cut_and_paste::cut_and_paste(&oracle, &template, target, 16)?;
+        let cracked_prof = decrypt_prof(&pasted)?;
+
+        expect("admin", &cracked_prof.role.to_string())?;
+        Ok(Output {
+            report: format!("{:?}", cracked_prof),
+            answer: cracked_prof.role.to_string(),
+        })
+    }
+}
+
+pub struct Challenge14;
+
+impl Challenge for Challenge14 {
+    fn number(&self) -> u32 {
+        14
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "Byte-at-a-time ECB decryption (Harder)"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let unknown_content = unknown_content()?;
+        let oracle = cypher::make_oracle(&unknown_content, OracleMode::Prefixing);
+
+        // the prefix is under a block, so some length of input between 33
+        // and 47 bytes first fills out the prefix's block and two more
+        let mut prefix_len = None;
+        for vector_len in 33..48 {
+            if oracle(&vec![b'X'; vector_len])?.dupe_blocks(16) > 0 {
+                prefix_len = Some(48 - vector_len);
+                break;
+            }
+        }
+        let prefix_len =
+            prefix_len.ok_or(Error::NoCandidate("no input length lines up the prefix"))?;
+
+        let unknown_len = appended_len(&oracle, prefix_len)?;
+        let decoded = decrypt_appended(&oracle, 16 - prefix_len, 1, unknown_len)?;
+
+        Ok(Output::answer(as_string(&decoded)))
+    }
+}
+
+pub struct Challenge15;
+
+impl Challenge for Challenge15 {
+    fn number(&self) -> u32 {
+        15
+    }
+
+    fn set(&self) -> u32 {
+        2
+    }
+
+    fn title(&self) -> &'static str {
+        "PKCS#7 padding validation"
+    }
+
+    fn run(&self) -> Result<Output> {
+        let cases: [(&[u8], Option<usize>); 3] = [
+            (b"ICE ICE BABY\x04\x04\x04\x04", Some(4)),
+            (b"ICE ICE BABY\x05\x05\x05\x05", None),
+            (b"ICE ICE BABY\x01\x02\x03\x04", None),
+        ];
+
+        let mut report = vec![];
+        for (buffer, expected) in cases {
+            let got = buffer.to_vec().is_padded_for_blocksize(16);
+            expect(&format!("{:?}", expected), &format!("{:?}", got))?;
+            report.push(format!("{:?} => {:?}", as_string(&buffer.to_vec()), got));
+        }
+
+        Ok(Output {
+            report: report.join("\n"),
+            answer: String::from("ok"),
+        })
+    }
+}
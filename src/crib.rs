@@ -0,0 +1,239 @@
+use crate::cypher::score_buffer_as_english;
+use crate::error::{Error, Result};
+
+// a recovered keystream byte, and how much to trust it: 1.0 for known
+// plaintext, otherwise the share of cyphertexts it decrypts to letters or
+// spaces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeystreamByte {
+    pub value: u8,
+    pub confidence: f64,
+}
+
+// the crib tried at one offset of one cyphertext, with what that makes every
+// other cyphertext say there, and how english that looks. lower scores are
+// better, as with the rest of the scorers
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub offset: usize,
+    pub fragments: Vec<(usize, Vec<u8>)>,
+    pub score: f64,
+}
+
+// several cyphertexts XORed with the same keystream. XORing any two cancels
+// the keystream, so a guess at a stretch of one plaintext gives away the
+// same stretch of every other
+pub struct CribDragger {
+    cyphertexts: Vec<Vec<u8>>,
+    keystream: Vec<Option<KeystreamByte>>,
+}
+
+fn is_text(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte == b' ' || byte == b'\n'
+}
+
+fn is_english(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b' '
+}
+
+impl CribDragger {
+    pub fn new(cyphertexts: Vec<Vec<u8>>) -> CribDragger {
+        let len = cyphertexts.iter().map(Vec::len).max().unwrap_or(0);
+
+        CribDragger {
+            cyphertexts,
+            keystream: vec![None; len],
+        }
+    }
+
+    pub fn cyphertexts(&self) -> &[Vec<u8>] {
+        &self.cyphertexts
+    }
+
+    pub fn keystream(&self) -> &[Option<KeystreamByte>] {
+        &self.keystream
+    }
+
+    fn cyphertext(&self, index: usize) -> Result<&Vec<u8>> {
+        self.cyphertexts.get(index).ok_or(Error::OutOfRange(index))
+    }
+
+    // what the other cyphertexts decrypt to, if crib sits at offset in
+    // cyphertext index. None if the crib runs off the end of it
+    fn fragments(&self, index: usize, offset: usize, crib: &[u8]) -> Option<Vec<(usize, Vec<u8>)>> {
+        let cyphertext = &self.cyphertexts[index];
+        if offset + crib.len() > cyphertext.len() {
+            return None;
+        }
+        let keystream: Vec<u8> = crib
+            .iter()
+            .zip(&cyphertext[offset..])
+            .map(|(p, c)| p ^ c)
+            .collect();
+
+        let fragments = self
+            .cyphertexts
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(other, cyphertext)| {
+                let fragment = cyphertext
+                    .iter()
+                    .skip(offset)
+                    .zip(&keystream)
+                    .map(|(c, k)| c ^ k)
+                    .collect();
+                (other, fragment)
+            })
+            .filter(|(_, fragment): &(usize, Vec<u8>)| !fragment.is_empty())
+            .collect();
+        Some(fragments)
+    }
+
+    // slides crib along cyphertext index, keeping the placements where every
+    // other cyphertext comes out as printable text, best first
+    pub fn drag(&self, index: usize, crib: &[u8]) -> Result<Vec<Placement>> {
+        let len = self.cyphertext(index)?.len();
+
+        let mut placements: Vec<_> = (0..len)
+            .filter_map(|offset| {
+                let fragments = self.fragments(index, offset, crib)?;
+                if fragments.is_empty() {
+                    return None;
+                }
+                if !fragments
+                    .iter()
+                    .all(|(_, fragment)| fragment.iter().all(|byte| is_text(*byte)))
+                {
+                    return None;
+                }
+
+                let scores: Vec<_> = fragments
+                    .iter()
+                    .map(|(_, fragment)| score_buffer_as_english(fragment))
+                    .collect();
+                if scores.iter().any(|score| *score < 0.0) {
+                    return None;
+                }
+
+                Some(Placement {
+                    offset,
+                    score: scores.iter().sum::<f64>() / scores.len() as f64,
+                    fragments,
+                })
+            })
+            .collect();
+
+        placements.sort_by(|a, b| a.score.total_cmp(&b.score));
+        Ok(placements)
+    }
+
+    // takes the crib as the plaintext of cyphertext index at offset, adding
+    // the keystream under it. a byte already recovered is only replaced by
+    // one held with more confidence, and agreeing guesses reinforce it
+    pub fn place(&mut self, index: usize, offset: usize, crib: &[u8]) -> Result<()> {
+        self.add_keystream(index, offset, crib, None)
+    }
+
+    // as place, but for plaintext that's known rather than guessed
+    pub fn known_plaintext(&mut self, index: usize, offset: usize, plaintext: &[u8]) -> Result<()> {
+        self.add_keystream(index, offset, plaintext, Some(1.0))
+    }
+
+    fn add_keystream(
+        &mut self,
+        index: usize,
+        offset: usize,
+        plaintext: &[u8],
+        confidence: Option<f64>,
+    ) -> Result<()> {
+        let cyphertext = self.cyphertext(index)?;
+        if offset + plaintext.len() > cyphertext.len() {
+            return Err(Error::OutOfRange(offset + plaintext.len()));
+        }
+
+        let guessed: Vec<_> = plaintext
+            .iter()
+            .zip(&cyphertext[offset..])
+            .enumerate()
+            .map(|(i, (p, c))| {
+                let value = p ^ c;
+                let confidence = confidence.unwrap_or_else(|| self.confidence(offset + i, value));
+                KeystreamByte { value, confidence }
+            })
+            .collect();
+
+        for (known, guess) in self.keystream[offset..].iter_mut().zip(guessed) {
+            *known = match known {
+                Some(known) if known.value == guess.value => Some(KeystreamByte {
+                    value: known.value,
+                    confidence: 1.0 - (1.0 - known.confidence) * (1.0 - guess.confidence),
+                }),
+                Some(known) if known.confidence >= guess.confidence => Some(*known),
+                _ => Some(guess),
+            };
+        }
+
+        Ok(())
+    }
+
+    // the share of cyphertexts reaching position whose byte there decrypts
+    // to a letter or a space under value
+    fn confidence(&self, position: usize, value: u8) -> f64 {
+        let bytes: Vec<_> = self
+            .cyphertexts
+            .iter()
+            .filter_map(|cyphertext| cyphertext.get(position))
+            .collect();
+        let english = bytes
+            .iter()
+            .filter(|byte| is_english(**byte ^ value))
+            .count();
+
+        english as f64 / bytes.len() as f64
+    }
+
+    // cyphertext index decrypted as far as the keystream goes, with None for
+    // bytes under keystream that isn't known yet
+    pub fn decrypt(&self, index: usize) -> Result<Vec<Option<u8>>> {
+        Ok(self
+            .cyphertext(index)?
+            .iter()
+            .zip(&self.keystream)
+            .map(|(c, k)| k.map(|k| c ^ k.value))
+            .collect())
+    }
+}
+
+#[test]
+fn test_crib_dragging() {
+    use crate::byte_buffer::{self, ByteBuffer};
+
+    let plaintexts: [&[u8]; 3] = [
+        b"we attack the castle at dawn",
+        b"the castle will hold till noon",
+        b"send more men to the east gate",
+    ];
+    let keystream = Vec::<u8>::from_rand_bytes(32);
+    let cyphertexts: Vec<_> = plaintexts
+        .iter()
+        .map(|plaintext| byte_buffer::xor(&plaintext.to_vec(), &keystream))
+        .collect();
+
+    let mut dragger = CribDragger::new(cyphertexts);
+    let placements = dragger.drag(0, b" the castle ").unwrap();
+    let best = placements.first().unwrap();
+    assert_eq!(best.offset, 9);
+    assert_eq!(best.fragments[0], (1, b"e will hold ".to_vec()));
+
+    dragger.place(0, best.offset, b" the castle ").unwrap();
+    let decrypted = dragger.decrypt(1).unwrap();
+    assert_eq!(decrypted[9], Some(b'e'));
+    assert_eq!(decrypted[0], None);
+    assert!(dragger.keystream()[9].unwrap().confidence > 0.9);
+
+    dragger.known_plaintext(2, 0, b"send more").unwrap();
+    assert_eq!(dragger.keystream()[0].unwrap().confidence, 1.0);
+    assert_eq!(dragger.decrypt(0).unwrap()[0], Some(b'w'));
+    assert!(dragger.place(0, 20, b" the castle ").is_err());
+}
@@ -0,0 +1,180 @@
+use crate::byte_buffer::ByteBuffer;
+use crate::error::{Error, Result};
+
+const FILLER: u8 = b'A';
+
+// what an ECB oracle wraps attacker input in before encrypting it: the text
+// before it, as much of the text after it as is known, and any bytes the
+// encoder strips or escapes out of the input
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+    pub forbidden: Vec<u8>,
+}
+
+impl Template {
+    pub fn new(prefix: &[u8], suffix: &[u8]) -> Template {
+        Template {
+            prefix: prefix.to_vec(),
+            suffix: suffix.to_vec(),
+            forbidden: vec![],
+        }
+    }
+
+    pub fn forbidding(mut self, bytes: &[u8]) -> Template {
+        self.forbidden.extend_from_slice(bytes);
+        self
+    }
+
+    // the plaintext around input, with None past the end of the known suffix
+    fn plaintext_at(&self, input: &[u8], position: usize) -> Option<u8> {
+        let (prefix_len, input_len) = (self.prefix.len(), input.len());
+        if position < prefix_len {
+            Some(self.prefix[position])
+        } else if position < prefix_len + input_len {
+            Some(input[position - prefix_len])
+        } else {
+            self.suffix.get(position - prefix_len - input_len).copied()
+        }
+    }
+}
+
+// one oracle query, and which block of its cyphertext to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedBlock {
+    pub input: Vec<u8>,
+    pub block: usize,
+}
+
+// finds an input that puts target_block at a block boundary. every input
+// length up to two blocks is tried against every block the plaintext would
+// span, with the input bytes inside that block set to whatever the target
+// needs there and the rest filler. a block only fits if the template's own
+// bytes in it already match the target, and the encoder would let the input
+// bytes through
+fn plan_block(template: &Template, target_block: &[u8], block_size: usize) -> Option<PlannedBlock> {
+    let prefix_len = template.prefix.len();
+
+    for input_len in 0..(2 * block_size) {
+        let plaintext_len = prefix_len + input_len + template.suffix.len();
+
+        for block in 0..plaintext_len.div_ceil(block_size) {
+            let start = block * block_size;
+            let mut input = vec![FILLER; input_len];
+            for (i, byte) in target_block.iter().enumerate() {
+                let position = start + i;
+                if position >= prefix_len && position < prefix_len + input_len {
+                    input[position - prefix_len] = *byte;
+                }
+            }
+
+            let fits = (0..block_size)
+                .all(|i| template.plaintext_at(&input, start + i) == Some(target_block[i]));
+            let allowed = input.iter().all(|byte| !template.forbidden.contains(byte));
+            if fits && allowed {
+                return Some(PlannedBlock { input, block });
+            }
+        }
+    }
+
+    None
+}
+
+// the queries that between them encrypt every block of target, padded the
+// way aes_ecb pads, in order
+pub fn plan_cut_and_paste(
+    template: &Template,
+    target: &[u8],
+    block_size: usize,
+) -> Result<Vec<PlannedBlock>> {
+    if block_size == 0 {
+        return Err(Error::InvalidInput("block size can't be zero"));
+    }
+    let mut padded = target.to_vec();
+    padded.pad_for_blocksize(block_size);
+
+    padded
+        .chunks(block_size)
+        .map(|target_block| {
+            plan_block(template, target_block, block_size).ok_or(Error::NoCandidate(
+                "no input lines a target block up with the template",
+            ))
+        })
+        .collect()
+}
+
+// runs the plan against the oracle and splices the blocks it asked for into
+// a cyphertext that decrypts to target
+pub fn cut_and_paste(
+    oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    template: &Template,
+    target: &[u8],
+    block_size: usize,
+) -> Result<Vec<u8>> {
+    let plan = plan_cut_and_paste(template, target, block_size)?;
+
+    let mut forged = vec![];
+    for planned in plan {
+        let cyphertext = oracle(&planned.input)?;
+        let block = cyphertext
+            .get(planned.block * block_size..(planned.block + 1) * block_size)
+            .ok_or(Error::OutOfRange(planned.block))?;
+        forged.extend_from_slice(block);
+    }
+
+    Ok(forged)
+}
+
+#[test]
+fn test_cut_and_paste() {
+    use crate::cypher;
+    use openssl::symm::Mode;
+
+    let key = Vec::<u8>::from_rand_bytes(16);
+    let decrypt = |cyphertext: &[u8]| {
+        let mut plaintext = cypher::aes_ecb(cyphertext, &key, Mode::Decrypt).unwrap();
+        if let Some(padded_by) = plaintext.is_padded_for_blocksize(16) {
+            plaintext.truncate(plaintext.len() - padded_by);
+        }
+        plaintext
+    };
+
+    // the uid is random, so only the start of the suffix is known
+    let oracle = |input: &[u8]| {
+        let mut email = input.to_vec();
+        email.retain(|byte| *byte != b'=' && *byte != b'&');
+        let encoded = [b"email=", &email[..], b"&role=user&uid=", b"4821"].concat();
+        cypher::aes_ecb(&encoded, &key, Mode::Encrypt)
+    };
+    let template = Template::new(b"email=", b"&role=user&uid=").forbidding(b"=&");
+    let target = b"email=fooooooooooo@bar.com&role=admin";
+    let forged = cut_and_paste(&oracle, &template, target, 16).unwrap();
+    assert_eq!(decrypt(&forged), target.to_vec());
+
+    // "admin" shares a block with "&role=" here, which only the template
+    // can supply
+    let target = b"email=foo@bar.com&role=admin";
+    assert!(plan_cut_and_paste(&template, target, 16).is_err());
+    assert!(plan_cut_and_paste(&template, target, 0).is_err());
+
+    let oracle = |input: &[u8]| {
+        let mut userdata = input.to_vec();
+        userdata.retain(|byte| *byte != b';' && *byte != b'=');
+        let encoded = [
+            b"comment1=cooking%20MCs;userdata=",
+            &userdata[..],
+            b";comment2=%20like%20a%20pound%20of%20bacon",
+        ]
+        .concat();
+        cypher::aes_ecb(&encoded, &key, Mode::Encrypt)
+    };
+    let template = Template::new(
+        b"comment1=cooking%20MCs;userdata=",
+        b";comment2=%20like%20a%20pound%20of%20bacon",
+    )
+    .forbidding(b";=");
+    let target = b"comment1=cooking%20MCs;userdata=admin;comment2=%20like%20a%20pound%20of%20bacon";
+    let forged = cut_and_paste(&oracle, &template, target, 16).unwrap();
+    assert_eq!(decrypt(&forged), target.to_vec());
+}
@@ -0,0 +1,190 @@
+// a small raw deflate (RFC 1951) compressor: greedy LZ77 over a 32k window,
+// emitted as a single block using the fixed huffman codes. it doesn't compress
+// as well as zlib, but it's enough to leak information through lengths
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 256;
+const HASH_SIZE: usize = 1 << 12;
+
+const LENGTH_BASES: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+struct BitWriter {
+    output: Vec<u8>,
+    bit_buffer: u32,
+    bit_count: u8,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            output: Vec::new(),
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    // deflate packs values starting from the least significant bit
+    fn write_bits(&mut self, value: u32, count: u8) {
+        self.bit_buffer |= value << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    // ...except for huffman codes, which are packed starting from their most
+    // significant bit
+    fn write_code(&mut self, code: u32, count: u8) {
+        let mut reversed = 0;
+        for i in 0..count {
+            reversed |= ((code >> i) & 1) << (count - 1 - i);
+        }
+        self.write_bits(reversed, count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        self.output
+    }
+}
+
+fn write_literal_or_length(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + (symbol - 144), 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xc0 + (symbol - 280), 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let length_code = LENGTH_BASES
+        .iter()
+        .rposition(|base| *base <= length)
+        .unwrap();
+    write_literal_or_length(writer, 257 + length_code as u32);
+    writer.write_bits(
+        (length - LENGTH_BASES[length_code]) as u32,
+        LENGTH_EXTRA_BITS[length_code],
+    );
+
+    let distance_code = DISTANCE_BASES
+        .iter()
+        .rposition(|base| *base <= distance)
+        .unwrap();
+    writer.write_code(distance_code as u32, 5);
+    writer.write_bits(
+        (distance - DISTANCE_BASES[distance_code]) as u32,
+        DISTANCE_EXTRA_BITS[distance_code],
+    );
+}
+
+fn hash_at(buffer: &[u8], i: usize) -> usize {
+    let key =
+        ((buffer[i] as usize) << 16) | ((buffer[i + 1] as usize) << 8) | buffer[i + 2] as usize;
+    (key.wrapping_mul(2654435761) >> 7) % HASH_SIZE
+}
+
+fn insert_hash(buffer: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + MIN_MATCH <= buffer.len() {
+        let hash = hash_at(buffer, i);
+        prev[i] = head[hash];
+        head[hash] = i;
+    }
+}
+
+pub fn compress(buffer: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // BFINAL set, BTYPE 01 (fixed huffman codes)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; buffer.len()];
+
+    let mut i = 0;
+    while i < buffer.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+
+        if i + MIN_MATCH <= buffer.len() {
+            let mut candidate = head[hash_at(buffer, i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let max_length = MAX_MATCH.min(buffer.len() - i);
+                let mut length = 0;
+                while length < max_length && buffer[candidate + length] == buffer[i + length] {
+                    length += 1;
+                }
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                }
+
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_length >= MIN_MATCH {
+            write_match(&mut writer, best_length, best_distance);
+            for j in i..(i + best_length) {
+                insert_hash(buffer, j, &mut head, &mut prev);
+            }
+            i += best_length;
+        } else {
+            write_literal_or_length(&mut writer, buffer[i] as u32);
+            insert_hash(buffer, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+
+    write_literal_or_length(&mut writer, 256);
+    writer.finish()
+}
+
+// expected outputs were checked by inflating them with zlib
+#[test]
+fn test_compress() {
+    // an empty fixed huffman block
+    assert_eq!(compress(b""), vec![0x03, 0x00]);
+    assert_eq!(compress(b"a"), vec![0x4b, 0x04, 0x00]);
+    // abc, then a 9 byte match 3 back
+    assert_eq!(
+        compress(b"abcabcabcabc"),
+        vec![0x4b, 0x4c, 0x4a, 0x86, 0x23, 0x00]
+    );
+    // a 16 byte match 17 back, which takes distance extra bits
+    assert_eq!(
+        compress(b"YELLOW SUBMARINE YELLOW SUBMARINE"),
+        vec![
+            0x8b, 0x74, 0xf5, 0xf1, 0xf1, 0x0f, 0x57, 0x08, 0x0e, 0x75, 0xf2, 0x75, 0x0c, 0xf2,
+            0xf4, 0x73, 0x55, 0x40, 0x17, 0x00, 0x00
+        ]
+    );
+    // a run longer than the longest match is split into several
+    assert_eq!(
+        compress(&[b'a'; 300]),
+        vec![0x4b, 0x1c, 0x05, 0x44, 0x03, 0x00]
+    );
+}
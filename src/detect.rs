@@ -0,0 +1,247 @@
+use crate::byte_buffer::ByteBuffer;
+use crate::error::Result;
+
+// how far input lengths are pushed when looking for the block size
+const MAX_BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    ECB,
+    CBC,
+    // CTR, OFB, or anything else that XORs the plaintext with a keystream.
+    // the keystream never depends on the plaintext in any of them, so an
+    // encryption oracle can't tell them apart
+    Stream,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Iv {
+    // the same input always gives the same output. for ECB that means there
+    // is nothing to fix, for the rest that the IV or nonce is reused
+    Fixed,
+    // the same input gives different output every time: a fresh IV or nonce,
+    // or a fresh key or random filler
+    Random,
+}
+
+// how a change to one plaintext byte shows up in the cyphertext
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    // only the same bits change, as with a keystream
+    Byte,
+    // the whole block containing it changes, and nothing else
+    Block,
+    // its block and every block after it change
+    Chained,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeReport {
+    // None when the output grows a byte at a time
+    pub block_size: Option<usize>,
+    pub mode: Mode,
+    pub iv: Iv,
+    pub padded: bool,
+    // only measurable when the same input gives the same output
+    pub propagation: Option<Propagation>,
+    // bytes of output beyond the input, with no input at all: IVs, nonces,
+    // anything the oracle wraps the input in, and padding
+    pub overhead: usize,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// the output lengths for every input length up to a few blocks. a length
+// preserving mode has no common factor to them, while a block mode pads
+// every one of them out to a multiple of the block size
+fn block_size(oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>) -> Result<Option<usize>> {
+    let mut size = 0;
+    for len in 0..=MAX_BLOCK_SIZE {
+        size = gcd(size, oracle(&vec![b'A'; len])?.len());
+    }
+
+    Ok((size > 1).then_some(size))
+}
+
+// a padded block mode holds the output length steady while the input grows
+// through a block, then steps it up by exactly one block once the input
+// crosses a boundary. random filler around the input, or a mode that only
+// sometimes rounds up, doesn't step that cleanly
+fn padded(oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>, block_size: usize) -> Result<bool> {
+    let lens = (0..=block_size * 3)
+        .map(|len| Ok(oracle(&vec![b'A'; len])?.len()))
+        .collect::<Result<Vec<usize>>>()?;
+    let steps: Vec<usize> = (1..lens.len())
+        .filter(|len| lens[*len] != lens[len - 1])
+        .collect();
+
+    Ok(steps.len() >= 2
+        && steps
+            .iter()
+            .all(|len| lens[*len] == lens[len - 1] + block_size)
+        && steps.windows(2).all(|pair| pair[1] - pair[0] == block_size))
+}
+
+// flips one bit well inside a long run of input, and sorts out what changed
+fn propagation(
+    oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    block_size: Option<usize>,
+) -> Result<Option<Propagation>> {
+    let block_size = block_size.unwrap_or(16);
+    let input = vec![b'A'; block_size * 6];
+    let mut flipped = input.clone();
+    flipped[block_size * 3] ^= 0x01;
+
+    let before = oracle(&input)?;
+    let after = oracle(&flipped)?;
+    if before.len() != after.len() {
+        return Ok(None);
+    }
+
+    let changed: Vec<usize> = (0..before.len())
+        .filter(|i| before[*i] != after[*i])
+        .collect();
+    let (Some(&first), Some(&last)) = (changed.first(), changed.last()) else {
+        return Ok(None);
+    };
+
+    Ok(
+        if changed.len() == 1 && (before[first] ^ after[first]) == 0x01 {
+            Some(Propagation::Byte)
+        } else if last / block_size == first / block_size {
+            Some(Propagation::Block)
+        } else if last / block_size == (before.len() - 1) / block_size {
+            Some(Propagation::Chained)
+        } else {
+            None
+        },
+    )
+}
+
+// enough identical input to fill two aligned blocks whatever comes before it,
+// which only ECB encrypts to two identical blocks
+fn looks_like_ecb(oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>, block_size: usize) -> Result<bool> {
+    Ok(oracle(&vec![b'A'; block_size * 3])?.dupe_blocks(block_size) > 0)
+}
+
+// works out as much about the mode as an encryption oracle gives away
+pub fn fingerprint(oracle: &dyn Fn(&[u8]) -> Result<Vec<u8>>) -> Result<ModeReport> {
+    let iv = if oracle(b"YELLOW SUBMARINE")? == oracle(b"YELLOW SUBMARINE")? {
+        Iv::Fixed
+    } else {
+        Iv::Random
+    };
+
+    let block_size = block_size(oracle)?;
+    let propagation = match iv {
+        Iv::Fixed => propagation(oracle, block_size)?,
+        Iv::Random => None,
+    };
+
+    let ecb = match block_size {
+        Some(size) => looks_like_ecb(oracle, size)?,
+        None => false,
+    };
+    let mode = match (block_size, propagation) {
+        (Some(_), _) if ecb => Mode::ECB,
+        (Some(_), Some(Propagation::Chained)) | (Some(_), None) => Mode::CBC,
+        (None, Some(Propagation::Byte)) | (None, None) => Mode::Stream,
+        _ => Mode::Unknown,
+    };
+
+    let padded = match block_size {
+        Some(size) => padded(oracle, size)?,
+        None => false,
+    };
+
+    Ok(ModeReport {
+        block_size,
+        mode,
+        iv,
+        padded,
+        propagation,
+        overhead: oracle(&[])?.len(),
+    })
+}
+
+#[test]
+fn test_fingerprint() {
+    use crate::cypher::{self, OracleMode};
+    use openssl::symm::Mode as CipherMode;
+
+    let secret = b"attack at dawn".to_vec();
+    let oracle = cypher::make_oracle(&secret, OracleMode::Prefixing);
+    let report = fingerprint(&|input: &[u8]| oracle(&input.to_vec())).unwrap();
+    assert_eq!(report.block_size, Some(16));
+    assert_eq!(report.mode, Mode::ECB);
+    assert_eq!(report.iv, Iv::Fixed);
+    assert_eq!(report.propagation, Some(Propagation::Block));
+    assert!(report.padded);
+
+    let key = Vec::<u8>::from_rand_bytes(16);
+    let iv = Vec::<u8>::from_rand_bytes(16);
+    let report =
+        fingerprint(&|input: &[u8]| cypher::aes_cbc(input, &key, &iv, CipherMode::Encrypt))
+            .unwrap();
+    assert_eq!(report.mode, Mode::CBC);
+    assert_eq!(report.iv, Iv::Fixed);
+    assert_eq!(report.propagation, Some(Propagation::Chained));
+    assert!(report.padded);
+
+    let report = fingerprint(&|input: &[u8]| {
+        let iv = Vec::<u8>::from_rand_bytes(16);
+        let cyphertext = cypher::aes_cbc(input, &key, &iv, CipherMode::Encrypt)?;
+        Ok([iv, cyphertext].concat())
+    })
+    .unwrap();
+    assert_eq!(report.mode, Mode::CBC);
+    assert_eq!(report.iv, Iv::Random);
+    assert_eq!(report.overhead, 16);
+    assert!(report.padded);
+
+    let report = fingerprint(&|input: &[u8]| cypher::aes_ctr(input, &key, 0)).unwrap();
+    assert_eq!(report.block_size, None);
+    assert_eq!(report.mode, Mode::Stream);
+    assert_eq!(report.iv, Iv::Fixed);
+    assert_eq!(report.propagation, Some(Propagation::Byte));
+    assert!(!report.padded);
+
+    let report = fingerprint(&|input: &[u8]| {
+        let nonce: u64 = rand::random();
+        let cyphertext = cypher::aes_ctr(input, &key, nonce)?;
+        Ok([nonce.to_le_bytes().to_vec(), cyphertext].concat())
+    })
+    .unwrap();
+    assert_eq!(report.mode, Mode::Stream);
+    assert_eq!(report.iv, Iv::Random);
+    assert_eq!(report.overhead, 8);
+    assert!(!report.padded);
+
+    // a block mode whose output length grows with the input in whole blocks,
+    // but not in step with it
+    let report = fingerprint(&|input: &[u8]| {
+        let blocks = input.len() / 16 + 1;
+        cypher::aes_ecb(&vec![b'A'; blocks * blocks * 16], &key, CipherMode::Encrypt)
+    })
+    .unwrap();
+    assert_eq!(report.block_size, Some(16));
+    assert!(!report.padded);
+
+    // a fresh key and mode every call, with random filler around the input
+    let report =
+        fingerprint(&|input: &[u8]| Ok(cypher::encryption_oracle(&input.to_vec())?.0)).unwrap();
+    assert_eq!(report.block_size, Some(16));
+    assert_eq!(report.iv, Iv::Random);
+
+    // the oracle's own errors come straight back
+    let report =
+        fingerprint(&|input: &[u8]| cypher::aes_ecb(input, &key[..15], CipherMode::Encrypt));
+    assert!(matches!(report, Err(crate::error::Error::KeyLength(15))));
+}
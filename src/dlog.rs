@@ -0,0 +1,378 @@
+use crate::bignum::{crt, BigInt};
+use crate::ec::{Point, WeierstrassCurve};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// how many independent walks kangaroo tries before giving up
+const KANGAROO_WALKS: u64 = 4;
+
+// a group written multiplicatively, which is all the discrete log solvers
+// need. the multiplicative group mod p is one, and so is an elliptic curve,
+// where op is point addition and pow is scalar multiplication
+pub trait Group {
+    type Element: Clone + Eq + Hash;
+
+    fn identity(&self) -> Self::Element;
+    fn op(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+
+    fn pow(&self, a: &Self::Element, k: &BigInt) -> Self::Element {
+        if k.is_negative() {
+            return self.pow(&self.inverse(a), &-k);
+        }
+
+        let mut result = self.identity();
+        for i in (0..k.num_bits()).rev() {
+            result = self.op(&result, &result);
+            if k.bit(i) {
+                result = self.op(&result, a);
+            }
+        }
+
+        result
+    }
+}
+
+// the integers mod a prime p under multiplication, as used for DH
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiplicativeGroup {
+    pub p: BigInt,
+}
+
+impl MultiplicativeGroup {
+    pub fn new(p: BigInt) -> MultiplicativeGroup {
+        MultiplicativeGroup { p }
+    }
+}
+
+impl Group for MultiplicativeGroup {
+    type Element = BigInt;
+
+    fn identity(&self) -> BigInt {
+        BigInt::one()
+    }
+
+    fn op(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b).modulo(&self.p)
+    }
+
+    fn inverse(&self, a: &BigInt) -> BigInt {
+        a.mod_inverse(&self.p).unwrap()
+    }
+
+    fn pow(&self, a: &BigInt, k: &BigInt) -> BigInt {
+        a.mod_pow(k, &self.p)
+    }
+}
+
+impl Group for WeierstrassCurve {
+    type Element = Point;
+
+    fn identity(&self) -> Point {
+        Point::Identity
+    }
+
+    fn op(&self, a: &Point, b: &Point) -> Point {
+        self.add(a, b)
+    }
+
+    fn inverse(&self, a: &Point) -> Point {
+        self.negate(a)
+    }
+
+    fn pow(&self, a: &Point, k: &BigInt) -> Point {
+        self.scalar_mul(a, k)
+    }
+}
+
+// a pseudorandom but deterministic choice of one of n classes for an element,
+// which the random walks below use to decide where to step
+fn partition<E: Hash>(element: &E, n: usize, salt: u64) -> usize {
+    let mut hasher = DefaultHasher::new();
+    salt.hash(&mut hasher);
+    element.hash(&mut hasher);
+    (hasher.finish() % n as u64) as usize
+}
+
+// baby-step giant-step: finds x in [0, bound) with g^x = y, in about the
+// square root of bound time and memory
+pub fn bsgs<G: Group>(group: &G, g: &G::Element, y: &G::Element, bound: &BigInt) -> Option<BigInt> {
+    let m = &bound.sqrt() + &BigInt::one();
+    let steps = m.to_u64()?;
+
+    let mut baby_steps = HashMap::new();
+    let mut current = group.identity();
+    for j in 0..steps {
+        baby_steps.entry(current.clone()).or_insert(j);
+        current = group.op(&current, g);
+    }
+
+    let giant_step = group.pow(g, &-&m);
+    let mut current = y.clone();
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&current) {
+            let x = &(&BigInt::from(i) * &m) + &BigInt::from(*j);
+            if &x < bound {
+                return Some(x);
+            }
+        }
+        current = group.op(&current, &giant_step);
+    }
+
+    None
+}
+
+// one step of the rho walk over g^a * y^b, tracking the exponents mod order
+fn rho_step<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    order: &BigInt,
+    (x, a, b): &mut (G::Element, BigInt, BigInt),
+) {
+    match partition(x, 3, 0) {
+        0 => {
+            *x = group.op(x, g);
+            *a = (&*a + &BigInt::one()).modulo(order);
+        }
+        1 => {
+            *x = group.op(x, x);
+            *a = (&*a * &BigInt::from(2u32)).modulo(order);
+            *b = (&*b * &BigInt::from(2u32)).modulo(order);
+        }
+        _ => {
+            *x = group.op(x, y);
+            *b = (&*b + &BigInt::one()).modulo(order);
+        }
+    }
+}
+
+// pollard's rho: finds the log of y to the base g, where g has the given
+// order, in about the square root of the order time and constant memory.
+// walks from random starting points until two of them collide, with floyd's
+// cycle finding
+pub fn pollard_rho<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    order: &BigInt,
+) -> Option<BigInt> {
+    for _ in 0..8 {
+        let a = BigInt::random_below(order);
+        let b = BigInt::random_below(order);
+        let start = group.op(&group.pow(g, &a), &group.pow(y, &b));
+
+        let mut tortoise = (start, a, b);
+        let mut hare = tortoise.clone();
+        loop {
+            rho_step(group, g, y, order, &mut tortoise);
+            rho_step(group, g, y, order, &mut hare);
+            rho_step(group, g, y, order, &mut hare);
+            if tortoise.0 == hare.0 {
+                break;
+            }
+        }
+
+        // g^a1 y^b1 = g^a2 y^b2, so (b1 - b2)x = a2 - a1 mod order. when b1 - b2
+        // shares a factor d with the order there are d solutions to try
+        let db = (&tortoise.2 - &hare.2).modulo(order);
+        let da = (&hare.1 - &tortoise.1).modulo(order);
+        let d = db.gcd(order);
+        if db.is_zero() || !(&da % &d).is_zero() || d > BigInt::from(1u32 << 16) {
+            continue;
+        }
+
+        let reduced_order = order / &d;
+        let x0 = (&(&da / &d) * &(&db / &d).mod_inverse(&reduced_order)?).modulo(&reduced_order);
+        for k in 0..d.to_u64()? {
+            let x = &x0 + &(&BigInt::from(k) * &reduced_order);
+            if group.pow(g, &x) == *y {
+                return Some(x);
+            }
+        }
+    }
+
+    None
+}
+
+// pollard's kangaroo: finds x in [low, high] with g^x = y, in about the
+// square root of the interval's width, if there is one
+pub fn kangaroo<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    low: &BigInt,
+    high: &BigInt,
+) -> Option<BigInt> {
+    // a walk misses the trap now and then. a fresh partition gives a
+    // different pair of walks, so a few of them all missing is unlikely
+    (0..KANGAROO_WALKS).find_map(|salt| kangaroo_walk(group, g, y, low, high, salt))
+}
+
+fn kangaroo_walk<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    low: &BigInt,
+    high: &BigInt,
+    salt: u64,
+) -> Option<BigInt> {
+    let width = high - low;
+    let k = width.num_bits() / 2 + 3;
+    let jumps: Vec<BigInt> = (0..k).map(|i| &BigInt::one() << i).collect();
+    let jump_elements: Vec<G::Element> = jumps.iter().map(|jump| group.pow(g, jump)).collect();
+
+    let mean = jumps.iter().fold(BigInt::zero(), |sum, jump| &sum + jump) / BigInt::from(k);
+    let steps = (&mean * &BigInt::from(4u32)).to_u64()?;
+
+    // the tame kangaroo starts at the top of the interval and leaves a trap
+    let mut tame_distance = BigInt::zero();
+    let mut tame = group.pow(g, high);
+    for _ in 0..steps {
+        let jump = partition(&tame, k, salt);
+        tame_distance = &tame_distance + &jumps[jump];
+        tame = group.op(&tame, &jump_elements[jump]);
+    }
+
+    // the wild one starts at y, and gives up once it's passed the trap
+    let limit = &width + &tame_distance;
+    let mut wild_distance = BigInt::zero();
+    let mut wild = y.clone();
+    while wild_distance <= limit {
+        if wild == tame {
+            return Some(&(high + &tame_distance) - &wild_distance);
+        }
+        let jump = partition(&wild, k, salt);
+        wild_distance = &wild_distance + &jumps[jump];
+        wild = group.op(&wild, &jump_elements[jump]);
+    }
+
+    None
+}
+
+// the log in a subgroup of prime order: small ones by table, big ones by rho
+fn prime_order_dlog<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    order: &BigInt,
+) -> Option<BigInt> {
+    if order.num_bits() <= 32 {
+        bsgs(group, g, y, order)
+    } else {
+        pollard_rho(group, g, y, order)
+    }
+}
+
+// pohlig-hellman: finds the log of y to the base g, where g has the given
+// order and factors are the distinct primes dividing it. the log is found
+// mod each prime power in the order, one base p digit at a time, and the
+// pieces are combined with the CRT, so it's only as hard as the largest prime
+pub fn pohlig_hellman<G: Group>(
+    group: &G,
+    g: &G::Element,
+    y: &G::Element,
+    order: &BigInt,
+    factors: &[BigInt],
+) -> Option<BigInt> {
+    let mut residues = vec![];
+
+    for p in factors {
+        let mut prime_power = BigInt::one();
+        let mut exponent = 0;
+        while (&(order / &prime_power) % p).is_zero() {
+            prime_power = &prime_power * p;
+            exponent += 1;
+        }
+
+        // g_i and y_i live in the subgroup of order p^e, and gamma generates
+        // its subgroup of order p
+        let cofactor = order / &prime_power;
+        let g_i = group.pow(g, &cofactor);
+        let y_i = group.pow(y, &cofactor);
+        let gamma = group.pow(&g_i, &(&prime_power / p));
+
+        let mut x = BigInt::zero();
+        let mut p_k = BigInt::one();
+        for _ in 0..exponent {
+            // strip the digits found so far, and push the next one down into
+            // the subgroup of order p
+            let remaining = group.op(&y_i, &group.pow(&g_i, &-&x));
+            let h = group.pow(&remaining, &(&prime_power / &(&p_k * p)));
+            let digit = prime_order_dlog(group, &gamma, &h, p)?;
+            x = &x + &(&digit * &p_k);
+            p_k = &p_k * p;
+        }
+
+        residues.push((x, prime_power));
+    }
+
+    let (x, modulus) = crt(&residues)?;
+    if modulus != *order {
+        return None;
+    }
+    Some(x)
+}
+
+#[test]
+fn test_generic_dlogs() {
+    // g has prime order q mod p
+    let group = MultiplicativeGroup::new(BigInt::from(179539193358535643u64));
+    let q = BigInt::from(167208901u64);
+    let g = BigInt::from(125632774845711166u64);
+    let x = BigInt::random_below(&q);
+    let y = group.pow(&g, &x);
+
+    assert_eq!(bsgs(&group, &g, &y, &q), Some(x.clone()));
+    assert_eq!(pollard_rho(&group, &g, &y, &q), Some(x.clone()));
+
+    let low = &x - &BigInt::from(1u32 << 20);
+    let high = &x + &BigInt::from(1u32 << 12);
+    assert_eq!(kangaroo(&group, &g, &y, &low, &high), Some(x));
+}
+
+#[test]
+fn test_pohlig_hellman() {
+    // p - 1 = 2 * 3 * 17 * 19^2 * 31 * 41 * 43 * 79 * 83 * 89 * 113^2, and 6
+    // generates the whole group
+    let p = BigInt::from(14995931815654209943u64);
+    let group = MultiplicativeGroup::new(p.clone());
+    let order = &p - &BigInt::one();
+    let factors: Vec<BigInt> = [2u32, 3, 17, 19, 31, 41, 43, 79, 83, 89, 113]
+        .iter()
+        .map(|f| BigInt::from(*f))
+        .collect();
+    let g = BigInt::from(6u32);
+    let x = BigInt::random_below(&order);
+    let y = group.pow(&g, &x);
+
+    assert_eq!(
+        order.small_prime_factors(1 << 8),
+        [2, 3, 17, 19, 31, 41, 43, 79, 83, 89, 113]
+    );
+    assert_eq!(pohlig_hellman(&group, &g, &y, &order, &factors), Some(x));
+
+    // and the same on a curve, in the subgroup of order 11 * 107 * 197 * 1621
+    // of the twist of the cryptopals curve
+    let curve = crate::ec::cryptopals_group().curve.quadratic_twist();
+    let twist_order = BigInt::from_dec_str(crate::ec::CRYPTOPALS_TWIST_ORDER).unwrap();
+    let subgroup_order = BigInt::from(11u64 * 107 * 197 * 1621);
+    let factors: Vec<BigInt> = [11u32, 107, 197, 1621]
+        .iter()
+        .map(|f| BigInt::from(*f))
+        .collect();
+    let g = loop {
+        let g = curve.scalar_mul(&curve.random_point(), &(&twist_order / &subgroup_order));
+        if curve.order_of(&g, &subgroup_order, &factors) == subgroup_order {
+            break g;
+        }
+    };
+    let x = BigInt::random_below(&subgroup_order);
+    let y = curve.scalar_mul(&g, &x);
+
+    assert_eq!(
+        pohlig_hellman(&curve, &g, &y, &subgroup_order, &factors),
+        Some(x)
+    );
+}
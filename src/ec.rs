@@ -0,0 +1,342 @@
+use crate::bignum::BigInt;
+
+// the curve from cryptopals set 8, y^2 = x^3 - 95051x + 11279326, whose
+// group has order 8q. its Montgomery form is v^2 = u^3 + 534u^2 + u, with
+// u = x - 178
+pub const CRYPTOPALS_P: &str = "233970423115425145524320034830162017933";
+pub const CRYPTOPALS_A: i64 = -95051;
+pub const CRYPTOPALS_B: i64 = 11279326;
+pub const CRYPTOPALS_GX: &str = "182";
+pub const CRYPTOPALS_GY: &str = "85518893674295321206118380980485522083";
+pub const CRYPTOPALS_Q: &str = "29246302889428143187362802287225875743";
+pub const CRYPTOPALS_CURVE_ORDER: &str = "233970423115425145498902418297807005944";
+pub const CRYPTOPALS_MONTGOMERY_A: i64 = 534;
+pub const CRYPTOPALS_MONTGOMERY_BASE_U: i64 = 4;
+pub const CRYPTOPALS_TWIST_ORDER: &str = "233970423115425145549737651362517029924";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Point {
+    Identity,
+    Affine(BigInt, BigInt),
+}
+
+// a short Weierstrass curve y^2 = x^3 + ax + b over the integers mod a prime
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeierstrassCurve {
+    pub p: BigInt,
+    pub a: BigInt,
+    pub b: BigInt,
+}
+
+impl WeierstrassCurve {
+    pub fn new(p: BigInt, a: BigInt, b: BigInt) -> WeierstrassCurve {
+        WeierstrassCurve {
+            a: a.modulo(&p),
+            b: b.modulo(&p),
+            p,
+        }
+    }
+
+    fn rhs(&self, x: &BigInt) -> BigInt {
+        (&(&(x * x) * x) + &(&(&self.a * x) + &self.b)).modulo(&self.p)
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match point {
+            Point::Identity => true,
+            Point::Affine(x, y) => (y * y).modulo(&self.p) == self.rhs(x),
+        }
+    }
+
+    pub fn negate(&self, point: &Point) -> Point {
+        match point {
+            Point::Identity => Point::Identity,
+            Point::Affine(x, y) => Point::Affine(x.clone(), (-y).modulo(&self.p)),
+        }
+    }
+
+    pub fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let (x1, y1, x2, y2) = match (p1, p2) {
+            (Point::Identity, _) => return p2.clone(),
+            (_, Point::Identity) => return p1.clone(),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => (x1, y1, x2, y2),
+        };
+
+        if *p1 == self.negate(p2) {
+            return Point::Identity;
+        }
+
+        let slope = if p1 == p2 {
+            let numerator = &(&BigInt::from(3u32) * &(x1 * x1)) + &self.a;
+            let denominator = (&BigInt::from(2u32) * y1).mod_inverse(&self.p).unwrap();
+            (&numerator * &denominator).modulo(&self.p)
+        } else {
+            let denominator = (x2 - x1).mod_inverse(&self.p).unwrap();
+            (&(y2 - y1) * &denominator).modulo(&self.p)
+        };
+
+        let x3 = (&(&(&slope * &slope) - x1) - x2).modulo(&self.p);
+        let y3 = (&(&slope * &(x1 - &x3)) - y1).modulo(&self.p);
+        Point::Affine(x3, y3)
+    }
+
+    pub fn double(&self, point: &Point) -> Point {
+        self.add(point, point)
+    }
+
+    pub fn scalar_mul(&self, point: &Point, k: &BigInt) -> Point {
+        if k.is_negative() {
+            return self.scalar_mul(&self.negate(point), &-k);
+        }
+
+        let mut result = Point::Identity;
+        for i in (0..k.num_bits()).rev() {
+            result = self.double(&result);
+            if k.bit(i) {
+                result = self.add(&result, point);
+            }
+        }
+
+        result
+    }
+
+    // one of the two points with this x coordinate, if there are any
+    pub fn lift_x(&self, x: &BigInt) -> Option<Point> {
+        let y = self.rhs(x).mod_sqrt(&self.p)?;
+        Some(Point::Affine(x.modulo(&self.p), y))
+    }
+
+    pub fn random_point(&self) -> Point {
+        loop {
+            if let Some(point) = self.lift_x(&BigInt::random_below(&self.p)) {
+                return point;
+            }
+        }
+    }
+
+    // the exact order of a point, given the group order (or any multiple of
+    // the point's order) and the distinct primes dividing it
+    pub fn order_of(&self, point: &Point, group_order: &BigInt, factors: &[BigInt]) -> BigInt {
+        let mut order = group_order.clone();
+
+        for factor in factors {
+            while (&order % factor).is_zero() {
+                let reduced = &order / factor;
+                if self.scalar_mul(point, &reduced) != Point::Identity {
+                    break;
+                }
+                order = reduced;
+            }
+        }
+
+        order
+    }
+
+    // the quadratic twist y^2 = x^3 + ad^2x + bd^3 for a non-residue d. if
+    // this curve has p + 1 - t points, the twist has p + 1 + t
+    pub fn quadratic_twist(&self) -> WeierstrassCurve {
+        let mut d = BigInt::from(2u32);
+        while d.legendre(&self.p) != -1 {
+            d = &d + &BigInt::one();
+        }
+
+        let d_squared = (&d * &d).modulo(&self.p);
+        WeierstrassCurve::new(
+            self.p.clone(),
+            &self.a * &d_squared,
+            &self.b * &(&d_squared * &d),
+        )
+    }
+}
+
+// a Montgomery curve Bv^2 = u^3 + Au^2 + u, used for x-only arithmetic
+#[derive(Clone, Debug, PartialEq)]
+pub struct MontgomeryCurve {
+    pub p: BigInt,
+    pub a: BigInt,
+    pub b: BigInt,
+}
+
+impl MontgomeryCurve {
+    pub fn new(p: BigInt, a: BigInt, b: BigInt) -> MontgomeryCurve {
+        MontgomeryCurve {
+            a: a.modulo(&p),
+            b: b.modulo(&p),
+            p,
+        }
+    }
+
+    // every u is either on the curve or on its twist. this says which
+    pub fn is_on_curve(&self, u: &BigInt) -> bool {
+        let rhs = &(&(u * u) * u) + &(&(&self.a * &(u * u)) + u);
+        let v_squared = &rhs * &self.b.mod_inverse(&self.p).unwrap();
+        v_squared.legendre(&self.p) != -1
+    }
+
+    // multiplies by k using only u coordinates. the result for the identity
+    // is 0. this works just as well for u on the twist, which is the catch
+    pub fn ladder(&self, u: &BigInt, k: &BigInt) -> BigInt {
+        let p = &self.p;
+        let four = BigInt::from(4u32);
+
+        let (mut u2, mut w2) = (BigInt::one(), BigInt::zero());
+        let (mut u3, mut w3) = (u.modulo(p), BigInt::one());
+
+        for i in (0..k.num_bits().max(p.num_bits())).rev() {
+            if k.bit(i) {
+                std::mem::swap(&mut u2, &mut u3);
+                std::mem::swap(&mut w2, &mut w3);
+            }
+
+            let u3_next = (&(&u2 * &u3) - &(&w2 * &w3)).modulo(p);
+            let u3_next = (&u3_next * &u3_next).modulo(p);
+            let w3_next = (&(&u2 * &w3) - &(&w2 * &u3)).modulo(p);
+            let w3_next = (u * &(&w3_next * &w3_next)).modulo(p);
+
+            let u2_squared = (&u2 * &u2).modulo(p);
+            let w2_squared = (&w2 * &w2).modulo(p);
+            let u2_next = (&u2_squared - &w2_squared).modulo(p);
+            let u2_next = (&u2_next * &u2_next).modulo(p);
+            let w2_next = &(&four * &(&u2 * &w2))
+                * &(&(&u2_squared + &(&self.a * &(&u2 * &w2))) + &w2_squared);
+            let w2_next = w2_next.modulo(p);
+
+            u2 = u2_next;
+            w2 = w2_next;
+            u3 = u3_next;
+            w3 = w3_next;
+
+            if k.bit(i) {
+                std::mem::swap(&mut u2, &mut u3);
+                std::mem::swap(&mut w2, &mut w3);
+            }
+        }
+
+        (&u2 * &w2.mod_pow(&(p - &BigInt::from(2u32)), p)).modulo(p)
+    }
+
+    fn a_over_3b(&self) -> BigInt {
+        let three_b = (&BigInt::from(3u32) * &self.b).modulo(&self.p);
+        (&self.a * &three_b.mod_inverse(&self.p).unwrap()).modulo(&self.p)
+    }
+
+    // the Weierstrass curve this is birationally equivalent to
+    pub fn to_weierstrass(&self) -> WeierstrassCurve {
+        let p = &self.p;
+        let a_squared = &self.a * &self.a;
+        let b_squared = &self.b * &self.b;
+
+        let a_numerator = &BigInt::from(3u32) - &a_squared;
+        let a_denominator = (&BigInt::from(3u32) * &b_squared).mod_inverse(p).unwrap();
+        let b_numerator =
+            &(&BigInt::from(2u32) * &(&a_squared * &self.a)) - &(&BigInt::from(9u32) * &self.a);
+        let b_denominator = (&BigInt::from(27u32) * &(&b_squared * &self.b))
+            .mod_inverse(p)
+            .unwrap();
+
+        WeierstrassCurve::new(
+            p.clone(),
+            &a_numerator * &a_denominator,
+            &b_numerator * &b_denominator,
+        )
+    }
+
+    pub fn u_to_x(&self, u: &BigInt) -> BigInt {
+        let b_inverse = self.b.mod_inverse(&self.p).unwrap();
+        (&(u * &b_inverse) + &self.a_over_3b()).modulo(&self.p)
+    }
+
+    pub fn x_to_u(&self, x: &BigInt) -> BigInt {
+        (&(x - &self.a_over_3b()) * &self.b).modulo(&self.p)
+    }
+}
+
+// a curve with a chosen generator of prime order
+#[derive(Clone, Debug)]
+pub struct CurveGroup {
+    pub curve: WeierstrassCurve,
+    pub generator: Point,
+    pub order: BigInt,
+    pub curve_order: BigInt,
+}
+
+pub fn cryptopals_group() -> CurveGroup {
+    let p = BigInt::from_dec_str(CRYPTOPALS_P).unwrap();
+    CurveGroup {
+        curve: WeierstrassCurve::new(p, BigInt::from(CRYPTOPALS_A), BigInt::from(CRYPTOPALS_B)),
+        generator: Point::Affine(
+            BigInt::from_dec_str(CRYPTOPALS_GX).unwrap(),
+            BigInt::from_dec_str(CRYPTOPALS_GY).unwrap(),
+        ),
+        order: BigInt::from_dec_str(CRYPTOPALS_Q).unwrap(),
+        curve_order: BigInt::from_dec_str(CRYPTOPALS_CURVE_ORDER).unwrap(),
+    }
+}
+
+pub fn cryptopals_montgomery_curve() -> MontgomeryCurve {
+    MontgomeryCurve::new(
+        BigInt::from_dec_str(CRYPTOPALS_P).unwrap(),
+        BigInt::from(CRYPTOPALS_MONTGOMERY_A),
+        BigInt::one(),
+    )
+}
+
+#[test]
+fn test_cryptopals_curve() {
+    let group = cryptopals_group();
+    let curve = &group.curve;
+    let g = &group.generator;
+
+    assert!(curve.contains(g));
+    assert_eq!(curve.scalar_mul(g, &group.order), Point::Identity);
+    assert_eq!(
+        curve.add(&curve.scalar_mul(g, &BigInt::from(5u32)), &curve.double(g)),
+        curve.scalar_mul(g, &BigInt::from(7u32))
+    );
+
+    let point = curve.random_point();
+    assert!(curve.contains(&point));
+    assert_eq!(
+        curve.scalar_mul(&point, &group.curve_order),
+        Point::Identity
+    );
+    let factors = [BigInt::from(2u32), group.order.clone()];
+    let order = curve.order_of(&point, &group.curve_order, &factors);
+    assert_eq!(curve.scalar_mul(&point, &order), Point::Identity);
+    assert!((&group.curve_order % &order).is_zero());
+
+    let twist = curve.quadratic_twist();
+    let twist_order = BigInt::from_dec_str(CRYPTOPALS_TWIST_ORDER).unwrap();
+    assert_eq!(
+        twist.scalar_mul(&twist.random_point(), &twist_order),
+        Point::Identity
+    );
+}
+
+#[test]
+fn test_montgomery_ladder() {
+    let group = cryptopals_group();
+    let montgomery = cryptopals_montgomery_curve();
+    let base_u = BigInt::from(CRYPTOPALS_MONTGOMERY_BASE_U);
+
+    assert_eq!(montgomery.to_weierstrass(), group.curve);
+    assert_eq!(
+        montgomery.u_to_x(&base_u),
+        BigInt::from_dec_str(CRYPTOPALS_GX).unwrap()
+    );
+    assert!(montgomery.ladder(&base_u, &group.order).is_zero());
+
+    let k = BigInt::random_below(&group.order);
+    let expected = match group.curve.scalar_mul(&group.generator, &k) {
+        Point::Affine(x, _) => montgomery.x_to_u(&x),
+        Point::Identity => BigInt::zero(),
+    };
+    assert_eq!(montgomery.ladder(&base_u, &k), expected);
+
+    let mut twist_u = BigInt::random_below(&montgomery.p);
+    while montgomery.is_on_curve(&twist_u) {
+        twist_u = BigInt::random_below(&montgomery.p);
+    }
+    let twist_order = BigInt::from_dec_str(CRYPTOPALS_TWIST_ORDER).unwrap();
+    assert!(montgomery.ladder(&twist_u, &twist_order).is_zero());
+}
@@ -0,0 +1,287 @@
+use crate::bignum::{crt, BigInt};
+use crate::dlog;
+use crate::ec::{CurveGroup, MontgomeryCurve, Point, WeierstrassCurve};
+use crate::error::{Error, Result};
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+
+// curves with the same a as the cryptopals curve but a different b, and their
+// orders. the addition formulas never look at b, so a victim that doesn't
+// check its input will happily do arithmetic on them, and each has a handful
+// of small subgroups to confine the result to
+pub const INVALID_CURVES: [(i64, &str); 3] = [
+    (210, "233970423115425145550826547352470124412"),
+    (504, "233970423115425145544350131142039591210"),
+    (727, "233970423115425145545378039958152057148"),
+];
+
+const BOB_MESSAGE: &[u8] = b"crazy flamboyant for the rap enthusiasts";
+
+// a message and its HMAC-SHA256 tag, under a key derived from the shared secret
+pub type TaggedMessage = (Vec<u8>, Vec<u8>);
+pub type EcdhOracle<'a> = Box<dyn Fn(&Point) -> Result<TaggedMessage> + 'a>;
+pub type TwistOracle<'a> = Box<dyn Fn(&BigInt) -> Result<TaggedMessage> + 'a>;
+
+pub fn hmac_sha256(key: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(msg)?;
+    Ok(signer.sign_to_vec()?)
+}
+
+fn point_mac_key(point: &Point) -> Result<Vec<u8>> {
+    let encoded = match point {
+        Point::Identity => vec![],
+        Point::Affine(x, y) => [x.to_bytes_be(), b",".to_vec(), y.to_bytes_be()].concat(),
+    };
+    Ok(hash(MessageDigest::sha256(), &encoded)?.to_vec())
+}
+
+fn u_mac_key(u: &BigInt) -> Result<Vec<u8>> {
+    Ok(hash(MessageDigest::sha256(), &u.to_bytes_be())?.to_vec())
+}
+
+// bob, who takes anyone's public point at face value, multiplies it by his
+// secret and tags a message with the result
+pub fn make_ecdh_oracle(group: &CurveGroup, secret: BigInt) -> EcdhOracle<'_> {
+    Box::new(move |public: &Point| {
+        let shared = group.curve.scalar_mul(public, &secret);
+        let tag = hmac_sha256(&point_mac_key(&shared)?, BOB_MESSAGE)?;
+        Ok((BOB_MESSAGE.to_vec(), tag))
+    })
+}
+
+// the same bob, doing x-only ECDH with the Montgomery ladder
+pub fn make_twist_oracle(curve: &MontgomeryCurve, secret: BigInt) -> TwistOracle<'_> {
+    Box::new(move |public: &BigInt| {
+        let shared = curve.ladder(public, &secret);
+        let tag = hmac_sha256(&u_mac_key(&shared)?, BOB_MESSAGE)?;
+        Ok((BOB_MESSAGE.to_vec(), tag))
+    })
+}
+
+// a point of prime order r on a curve with the given order, which r divides.
+// the r-part of the group needn't be cyclic, so rather than multiplying by
+// order / r, every factor of r is stripped and then multiplied back in
+fn point_of_order(curve: &WeierstrassCurve, curve_order: &BigInt, r: u64) -> Point {
+    let r = BigInt::from(r);
+    let mut cofactor = curve_order.clone();
+    while (&cofactor % &r).is_zero() {
+        cofactor = &cofactor / &r;
+    }
+
+    loop {
+        let mut point = curve.scalar_mul(&curve.random_point(), &cofactor);
+        if point == Point::Identity {
+            continue;
+        }
+        loop {
+            let next = curve.scalar_mul(&point, &r);
+            if next == Point::Identity {
+                return point;
+            }
+            point = next;
+        }
+    }
+}
+
+// recovers bob's secret from its residues mod the small primes dividing the
+// orders of the invalid curves. for each prime, bob is sent a point of that
+// order, so his shared secret is one of only r points, and trying each of
+// them against the tag gives the secret mod r. primes are taken below
+// factor_bound until their product passes key_bound, which is the group
+// order for a real key
+pub fn invalid_curve_attack(
+    group: &CurveGroup,
+    oracle: &EcdhOracle,
+    factor_bound: u64,
+    key_bound: &BigInt,
+) -> Result<BigInt> {
+    let mut residues: Vec<(BigInt, BigInt)> = vec![];
+    let mut product = BigInt::one();
+
+    for (b, order) in INVALID_CURVES.iter() {
+        let curve = WeierstrassCurve::new(
+            group.curve.p.clone(),
+            group.curve.a.clone(),
+            BigInt::from(*b),
+        );
+        let order =
+            BigInt::from_dec_str(order).ok_or(Error::InvalidInput("invalid curve order"))?;
+
+        for r in order.small_prime_factors(factor_bound) {
+            let modulus = BigInt::from(r);
+            if &product > key_bound || residues.iter().any(|(_, m)| *m == modulus) {
+                continue;
+            }
+
+            let h = point_of_order(&curve, &order, r);
+            let (message, tag) = oracle(&h)?;
+
+            let mut guess = Point::Identity;
+            for k in 0..r {
+                if hmac_sha256(&point_mac_key(&guess)?, &message)? == tag {
+                    residues.push((BigInt::from(k), modulus.clone()));
+                    product = &product * &modulus;
+                    break;
+                }
+                guess = curve.add(&guess, &h);
+            }
+        }
+    }
+
+    if &product <= key_bound {
+        return Err(Error::NoCandidate(
+            "too few small subgroups to cover key_bound",
+        ));
+    }
+    let (secret, _) = crt(&residues).ok_or(Error::NoCandidate("residues don't combine"))?;
+    Ok(secret)
+}
+
+// a u on the twist whose order is the prime r
+fn twist_u_of_order(curve: &MontgomeryCurve, twist_order: &BigInt, r: u64) -> BigInt {
+    let cofactor = twist_order / &BigInt::from(r);
+    loop {
+        let u = BigInt::random_below(&curve.p);
+        if curve.is_on_curve(&u) {
+            continue;
+        }
+        let u = curve.ladder(&u, &cofactor);
+        if !u.is_zero() {
+            return u;
+        }
+    }
+}
+
+// the twist attack on x-only ECDH, where bob can't be fed invalid curves but
+// checks nothing about u either, and half of all u are on the twist. its
+// small subgroups leak the secret mod each r, but only up to sign since u
+// can't tell k from -k. every combination of signs is tried, with the
+// kangaroo finding the rest of the secret below key_bound from bob's
+// public u, mapped over to the Weierstrass curve
+pub fn twist_attack(
+    curve: &MontgomeryCurve,
+    group: &CurveGroup,
+    oracle: &TwistOracle,
+    public_u: &BigInt,
+    factor_bound: u64,
+    key_bound: &BigInt,
+) -> Result<BigInt> {
+    let two = BigInt::from(2u32);
+    let twist_order = &(&two * &(&curve.p + &BigInt::one())) - &group.curve_order;
+
+    let mut residues: Vec<(u64, u64)> = vec![];
+    for r in twist_order.small_prime_factors(factor_bound) {
+        // the ladder returns 0 for the identity as well as the point of order 2
+        if r == 2 {
+            continue;
+        }
+
+        let u = twist_u_of_order(curve, &twist_order, r);
+        let (message, tag) = oracle(&u)?;
+        let mut k = None;
+        for guess in 0..=r / 2 {
+            let shared = curve.ladder(&u, &BigInt::from(guess));
+            if hmac_sha256(&u_mac_key(&shared)?, &message)? == tag {
+                k = Some(guess);
+                break;
+            }
+        }
+        residues.push((k.ok_or(Error::NoCandidate("no k matches bob's tag"))?, r));
+    }
+
+    let modulus = residues.iter().fold(BigInt::one(), |product, (_, r)| {
+        &product * &BigInt::from(*r)
+    });
+    let g = &group.generator;
+    let y = group
+        .curve
+        .lift_x(&curve.u_to_x(public_u))
+        .ok_or(Error::InvalidInput("public u isn't on the curve"))?;
+    let g_step = group.curve.scalar_mul(g, &modulus);
+
+    for signs in 0..(1u64 << residues.len()) {
+        let signed: Vec<(BigInt, BigInt)> = residues
+            .iter()
+            .enumerate()
+            .map(|(i, (k, r))| {
+                let k = if signs >> i & 1 == 1 { r - k } else { *k };
+                (BigInt::from(k), BigInt::from(*r))
+            })
+            .collect();
+        let (offset, _) = crt(&signed).ok_or(Error::NoCandidate("residues don't combine"))?;
+        if &offset >= key_bound {
+            continue;
+        }
+        let bound = (key_bound - &offset) / &modulus;
+
+        // bob's public u lifts to either his public point or its negation
+        for target in [y.clone(), group.curve.negate(&y)] {
+            let shifted = group
+                .curve
+                .add(&target, &group.curve.scalar_mul(g, &-&offset));
+            if let Some(m) =
+                dlog::kangaroo(&group.curve, &g_step, &shifted, &BigInt::zero(), &bound)
+            {
+                return Ok(&offset + &(&m * &modulus));
+            }
+        }
+    }
+
+    Err(Error::NoCandidate("no secret below key_bound"))
+}
+
+#[test]
+fn test_invalid_curve_orders() {
+    let group = crate::ec::cryptopals_group();
+
+    for (b, order) in INVALID_CURVES.iter() {
+        let curve = WeierstrassCurve::new(
+            group.curve.p.clone(),
+            group.curve.a.clone(),
+            BigInt::from(*b),
+        );
+        let order = BigInt::from_dec_str(order).unwrap();
+        assert_eq!(
+            curve.scalar_mul(&curve.random_point(), &order),
+            Point::Identity
+        );
+    }
+}
+
+#[test]
+fn test_invalid_curve_attack() {
+    let group = crate::ec::cryptopals_group();
+    let key_bound = &BigInt::one() << 64;
+    let secret = BigInt::random_below(&key_bound);
+    let oracle = make_ecdh_oracle(&group, secret.clone());
+
+    assert_eq!(
+        invalid_curve_attack(&group, &oracle, 1 << 12, &key_bound).unwrap(),
+        secret
+    );
+    assert!(matches!(
+        invalid_curve_attack(&group, &oracle, 1 << 4, &key_bound),
+        Err(Error::NoCandidate(_))
+    ));
+}
+
+#[test]
+fn test_twist_attack() {
+    let group = crate::ec::cryptopals_group();
+    let curve = crate::ec::cryptopals_montgomery_curve();
+    let key_bound = &BigInt::one() << 40;
+    let secret = BigInt::random_below(&key_bound);
+    let public_u = curve.ladder(
+        &BigInt::from(crate::ec::CRYPTOPALS_MONTGOMERY_BASE_U),
+        &secret,
+    );
+    let oracle = make_twist_oracle(&curve, secret.clone());
+
+    assert_eq!(
+        twist_attack(&curve, &group, &oracle, &public_u, 1 << 8, &key_bound).unwrap(),
+        secret
+    );
+}
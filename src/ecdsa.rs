@@ -0,0 +1,280 @@
+use crate::bignum::BigInt;
+use crate::ec::{CurveGroup, Point};
+use crate::error::{Error, Result};
+use crate::lattice::Matrix;
+use openssl::hash::{hash, MessageDigest};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub r: BigInt,
+    pub s: BigInt,
+}
+
+// SHA-256 of the message, truncated to the bit length of the group order
+pub fn hash_message(group: &CurveGroup, msg: &[u8]) -> Result<BigInt> {
+    let digest = BigInt::from_bytes_be(&hash(MessageDigest::sha256(), msg)?);
+    let excess = 256usize.saturating_sub(group.order.num_bits());
+    Ok(digest >> excess)
+}
+
+pub fn generate_keypair(group: &CurveGroup) -> (BigInt, Point) {
+    let private = BigInt::random_range(&BigInt::one(), &group.order);
+    let public = group.curve.scalar_mul(&group.generator, &private);
+    (private, public)
+}
+
+// None in the unlucky case that r or s comes out as zero
+fn sign_digest(
+    group: &CurveGroup,
+    private: &BigInt,
+    digest: &BigInt,
+    k: &BigInt,
+) -> Option<Signature> {
+    let n = &group.order;
+    let r = match group.curve.scalar_mul(&group.generator, k) {
+        Point::Identity => return None,
+        Point::Affine(x, _) => x.modulo(n),
+    };
+    let s = (&k.mod_inverse(n)? * &(digest + &(&r * private))).modulo(n);
+
+    if r.is_zero() || s.is_zero() {
+        return None;
+    }
+    Some(Signature { r, s })
+}
+
+// signs with a caller chosen nonce, which fails in the unlucky case that r or
+// s comes out as zero
+pub fn sign_with_nonce(
+    group: &CurveGroup,
+    private: &BigInt,
+    msg: &[u8],
+    k: &BigInt,
+) -> Result<Signature> {
+    sign_digest(group, private, &hash_message(group, msg)?, k)
+        .ok_or(Error::InvalidInput("nonce gives a zero r or s"))
+}
+
+pub fn sign(group: &CurveGroup, private: &BigInt, msg: &[u8]) -> Result<Signature> {
+    let digest = hash_message(group, msg)?;
+    loop {
+        let k = BigInt::random_range(&BigInt::one(), &group.order);
+        if let Some(signature) = sign_digest(group, private, &digest, &k) {
+            return Ok(signature);
+        }
+    }
+}
+
+// the point u1 G + u2 Q whose x coordinate a valid signature's r matches
+fn verification_point(
+    group: &CurveGroup,
+    public: &Point,
+    digest: &BigInt,
+    signature: &Signature,
+) -> Option<Point> {
+    let n = &group.order;
+    let zero = BigInt::zero();
+    if signature.r <= zero || &signature.r >= n || signature.s <= zero || &signature.s >= n {
+        return None;
+    }
+
+    let w = signature.s.mod_inverse(n)?;
+    let u1 = (digest * &w).modulo(n);
+    let u2 = (&signature.r * &w).modulo(n);
+    let curve = &group.curve;
+    Some(curve.add(
+        &curve.scalar_mul(&group.generator, &u1),
+        &curve.scalar_mul(public, &u2),
+    ))
+}
+
+pub fn verify(group: &CurveGroup, public: &Point, msg: &[u8], signature: &Signature) -> bool {
+    let Ok(digest) = hash_message(group, msg) else {
+        return false;
+    };
+    match verification_point(group, public, &digest, signature) {
+        Some(Point::Affine(x, _)) => x.modulo(&group.order) == signature.r,
+        _ => false,
+    }
+}
+
+pub struct SubstitutedKey {
+    pub group: CurveGroup,
+    pub private: BigInt,
+    pub public: Point,
+}
+
+// duplicate signature key selection: given someone else's signature, picks a
+// new generator and a keypair under it that the same signature verifies for.
+// verification computes R = u1 G + u2 Q, so any G' and d' with
+// (u1 + u2 d') G' = R will do, and G' = R / (u1 + u2 d') for a random d'
+pub fn substitute_key(
+    group: &CurveGroup,
+    public: &Point,
+    msg: &[u8],
+    signature: &Signature,
+) -> Result<SubstitutedKey> {
+    let n = &group.order;
+    let digest = hash_message(group, msg)?;
+    let r_point = verification_point(group, public, &digest, signature)
+        .ok_or(Error::InvalidInput("signature out of range"))?;
+
+    let w = signature
+        .s
+        .mod_inverse(n)
+        .ok_or(Error::InvalidInput("signature out of range"))?;
+    let u1 = (&digest * &w).modulo(n);
+    let u2 = (&signature.r * &w).modulo(n);
+
+    // a private key that makes u1 + u2 d' zero has no inverse, so pick again
+    let (private, t_inverse) = loop {
+        let private = BigInt::random_range(&BigInt::one(), n);
+        if let Some(t_inverse) = (&u1 + &(&u2 * &private)).mod_inverse(n) {
+            break (private, t_inverse);
+        }
+    };
+    let generator = group.curve.scalar_mul(&r_point, &t_inverse);
+    let public = group.curve.scalar_mul(&generator, &private);
+
+    Ok(SubstitutedKey {
+        group: CurveGroup {
+            generator,
+            ..group.clone()
+        },
+        private,
+        public,
+    })
+}
+
+pub type BiasedSigner<'a> = Box<dyn Fn(&[u8]) -> Result<Signature> + 'a>;
+
+// a signer whose nonces always have their low zero_bits bits clear
+pub fn make_biased_signer(
+    group: &CurveGroup,
+    private: BigInt,
+    zero_bits: usize,
+) -> BiasedSigner<'_> {
+    Box::new(move |msg: &[u8]| {
+        let digest = hash_message(group, msg)?;
+        loop {
+            let k = BigInt::random_below(&(&group.order >> zero_bits)) << zero_bits;
+            if let Some(signature) = sign_digest(group, &private, &digest, &k) {
+                return Ok(signature);
+            }
+        }
+    })
+}
+
+// recovers the private key from signatures whose nonces have zero_bits low
+// zero bits. with S = 2^zero_bits each nonce is S b_i for a small b_i, and
+// rearranging s k = H + r d gives b_i = d t_i - u_i mod q, for
+// t_i = r / (s S) and u_i = -H / (s S). in the lattice spanned by the rows
+//
+//     q S  0   ...  0    0
+//     0    q S ...  0    0
+//     ...
+//     t_1 S t_2 S ... 1  0
+//     u_1 S u_2 S ... 0  q
+//
+// the combination with d times the t row, minus the u row, is the unusually
+// short (S b_1, ..., S b_n, d, -q), which LLL should turn up
+pub fn biased_nonce_attack(
+    group: &CurveGroup,
+    public: &Point,
+    signatures: &[(Vec<u8>, Signature)],
+    zero_bits: usize,
+) -> Result<BigInt> {
+    let q = &group.order;
+    let scale = BigInt::one() << zero_bits;
+    let n = signatures.len();
+
+    let mut basis = vec![vec![BigInt::zero(); n + 2]; n + 2];
+    for (i, row) in basis.iter_mut().take(n).enumerate() {
+        row[i] = q * &scale;
+    }
+    for (i, (msg, signature)) in signatures.iter().enumerate() {
+        let inverse = (&signature.s * &scale)
+            .mod_inverse(q)
+            .ok_or(Error::InvalidInput("signature out of range"))?;
+        basis[n][i] = &(&signature.r * &inverse).modulo(q) * &scale;
+        basis[n + 1][i] = &(-&hash_message(group, msg)? * &inverse).modulo(q) * &scale;
+    }
+    basis[n][n] = BigInt::one();
+    basis[n + 1][n + 1] = q.clone();
+
+    Matrix::from(basis)
+        .lll()
+        .rows
+        .into_iter()
+        .map(|row| row.0)
+        .filter(|row| row[n + 1].abs() == *q)
+        .map(|row| {
+            if row[n + 1] == *q {
+                (-&row[n]).modulo(q)
+            } else {
+                row[n].modulo(q)
+            }
+        })
+        .find(|d| group.curve.scalar_mul(&group.generator, d) == *public)
+        .ok_or(Error::NoCandidate("no short vector gives the private key"))
+}
+
+#[test]
+fn test_ecdsa_key_substitution() {
+    let group = crate::ec::cryptopals_group();
+    let (private, public) = generate_keypair(&group);
+    let msg = b"hi mom";
+    let signature = sign(&group, &private, msg).unwrap();
+
+    assert!(verify(&group, &public, msg, &signature));
+    assert!(!verify(&group, &public, b"hi dad", &signature));
+
+    let substituted = substitute_key(&group, &public, msg, &signature).unwrap();
+    assert_ne!(substituted.public, public);
+    assert_eq!(
+        substituted
+            .group
+            .curve
+            .scalar_mul(&substituted.group.generator, &substituted.private),
+        substituted.public
+    );
+    assert!(verify(
+        &substituted.group,
+        &substituted.public,
+        msg,
+        &signature
+    ));
+    assert!(sign_with_nonce(&group, &private, msg, &BigInt::zero()).is_err());
+    let zero = Signature {
+        r: BigInt::zero(),
+        s: signature.s.clone(),
+    };
+    assert!(substitute_key(&group, &public, msg, &zero).is_err());
+}
+
+#[test]
+fn test_biased_nonce_attack() {
+    // far more bias than the 8 bits of the challenge, to keep the lattice small
+    let group = crate::ec::cryptopals_group();
+    let (private, public) = generate_keypair(&group);
+    let zero_bits = 32;
+    let signer = make_biased_signer(&group, private.clone(), zero_bits);
+
+    let signatures: Vec<(Vec<u8>, Signature)> = (0..8u32)
+        .map(|i| {
+            let msg = format!("message {}", i).into_bytes();
+            let signature = signer(&msg).unwrap();
+            assert!(verify(&group, &public, &msg, &signature));
+            (msg, signature)
+        })
+        .collect();
+
+    assert_eq!(
+        biased_nonce_attack(&group, &public, &signatures, zero_bits).unwrap(),
+        private
+    );
+    assert!(matches!(
+        biased_nonce_attack(&group, &public, &signatures[..1], zero_bits),
+        Err(Error::NoCandidate(_))
+    ));
+}
@@ -0,0 +1,123 @@
+use openssl::error::ErrorStack;
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeType {
+    Hex,
+    Base64,
+    Base32,
+    Base58,
+    Ascii85,
+    Z85,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    decode_type: DecodeType,
+    msg: String,
+}
+
+impl DecodeError {
+    pub fn new(decode_type: DecodeType, msg: &str) -> DecodeError {
+        DecodeError {
+            decode_type,
+            msg: String::from(msg),
+        }
+    }
+
+    pub fn decode_type(&self) -> DecodeType {
+        self.decode_type
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DecodeError decoding {:?}: {}",
+            self.decode_type, &self.msg
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug)]
+pub enum Error {
+    Decode(DecodeError),
+    // PKCS#7 padding that doesn't check out after decryption
+    Padding,
+    KeyLength(usize),
+    IvLength(usize),
+    // anything openssl itself refuses
+    Cipher(ErrorStack),
+    // text that doesn't read as what it's meant to be
+    Parse(String),
+    // arguments a function can't work with, whatever the key or oracle
+    InvalidInput(&'static str),
+    // two buffers that should have been the same length
+    LengthMismatch(usize, usize),
+    OutOfRange(usize),
+    InvalidMac,
+    // a token whose expiry time, in seconds since the epoch, has passed
+    Expired(u64),
+    // a breaker that found nothing worth returning
+    NoCandidate(&'static str),
+    // a search that gave up after this many tries
+    Exhausted(usize),
+    // a computed value that isn't the one it should have been
+    Mismatch { expected: String, got: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Decode(e) => write!(f, "{}", e),
+            Error::Padding => write!(f, "Invalid padding"),
+            Error::KeyLength(len) => write!(f, "Invalid key length {}", len),
+            Error::IvLength(len) => write!(f, "Invalid IV length {}", len),
+            Error::Cipher(e) => write!(f, "Cipher error: {}", e),
+            Error::Parse(msg) => write!(f, "{}", msg),
+            Error::InvalidInput(what) => write!(f, "Invalid input: {}", what),
+            Error::LengthMismatch(a, b) => write!(f, "Length mismatch: {} and {}", a, b),
+            Error::OutOfRange(index) => write!(f, "Index {} out of range", index),
+            Error::InvalidMac => write!(f, "Invalid MAC"),
+            Error::Expired(at) => write!(f, "Expired at {}", at),
+            Error::NoCandidate(what) => write!(f, "No candidate found: {}", what),
+            Error::Exhausted(tries) => write!(f, "Gave up after {} tries", tries),
+            Error::Mismatch { expected, got } => write!(f, "expected {}, got {}", expected, got),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode(e) => Some(e),
+            Error::Cipher(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Error {
+        Error::Decode(e)
+    }
+}
+
+impl From<ErrorStack> for Error {
+    fn from(e: ErrorStack) -> Error {
+        Error::Cipher(e)
+    }
+}
+
+// for the streaming encoders and decoders, whose Read and Write impls can
+// only return io errors
+pub(crate) fn invalid_data(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
@@ -1,55 +1,378 @@
-use crate::utils::{DecodeError, DecodeType};
-
-const HEX_CHAR_INDEXES: [char; 16] = [
-    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
-];
-
-pub fn decode_in_place(buffer: &mut Vec<u8>) -> Result<(), DecodeError> {
-    let byte_align_offset = buffer.len() % 2;
-
-    for i in 0..buffer.len() {
-        let is_upper_nibble = (i + byte_align_offset) % 2 == 0;
-        let char_index = HEX_CHAR_INDEXES
-            .iter()
-            .position(|c| *c == buffer[i] as char);
-        match char_index {
-            Some(char_index) => {
-                let byte_index = (i + byte_align_offset) / 2;
-                if is_upper_nibble {
-                    buffer[byte_index] = (char_index << 4) as u8;
-                } else {
-                    buffer[byte_index] |= char_index as u8;
-                }
-            }
-            None => {
-                return Err(DecodeError::new(
-                    DecodeType::Hex,
-                    "buffer contains invalid hex characters",
-                ))
-            }
-        }
+use crate::error::{invalid_data, DecodeError, DecodeType, Result};
+use std::io::{self, Read, Write};
+
+const LOWER_CHARS: &[u8; 16] = b"0123456789abcdef";
+const UPPER_CHARS: &[u8; 16] = b"0123456789ABCDEF";
+
+fn error(msg: &str) -> DecodeError {
+    DecodeError::new(DecodeType::Hex, msg)
+}
+
+// either case
+fn nibble(c: u8) -> std::result::Result<u8, DecodeError> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(error("buffer contains invalid hex characters")),
+    }
+}
+
+pub fn decode_in_place(buffer: &mut Vec<u8>) -> Result<()> {
+    if !buffer.len().is_multiple_of(2) {
+        return Err(error("buffer has an odd length").into());
     }
 
-    buffer.truncate((buffer.len() / 2) + byte_align_offset);
+    for i in 0..buffer.len() / 2 {
+        buffer[i] = (nibble(buffer[2 * i])? << 4) | nibble(buffer[2 * i + 1])?;
+    }
+    buffer.truncate(buffer.len() / 2);
 
     Ok(())
 }
 
-pub fn decode(buffer: &Vec<u8>) -> Result<Vec<u8>, DecodeError> {
-    let mut decoded = buffer.clone();
+pub fn decode(buffer: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = buffer.to_vec();
 
     decode_in_place(&mut decoded)?;
 
     Ok(decoded)
 }
 
-pub fn encode(buffer: &Vec<u8>) -> Vec<u8> {
-    let mut encoded = vec![0u8; buffer.len() * 2];
+pub fn encode(buffer: &[u8]) -> Vec<u8> {
+    HexConfig::PLAIN.encode(buffer)
+}
+
+// how encode lays bytes out: which case, what goes before each byte and
+// between them, and what goes around the lot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexConfig {
+    uppercase: bool,
+    byte_prefix: &'static str,
+    separator: &'static str,
+    open: &'static str,
+    close: &'static str,
+}
+
+impl HexConfig {
+    // 48656c6c6f
+    pub const PLAIN: HexConfig = HexConfig {
+        uppercase: false,
+        byte_prefix: "",
+        separator: "",
+        open: "",
+        close: "",
+    };
+    // 48:65:6c:6c:6f
+    pub const COLONS: HexConfig = HexConfig {
+        separator: ":",
+        ..HexConfig::PLAIN
+    };
+    // 48 65 6c 6c 6f
+    pub const SPACED: HexConfig = HexConfig {
+        separator: " ",
+        ..HexConfig::PLAIN
+    };
+    // 0x48 0x65 0x6c 0x6c 0x6f
+    pub const PREFIXED: HexConfig = HexConfig {
+        byte_prefix: "0x",
+        separator: " ",
+        ..HexConfig::PLAIN
+    };
+    // { 0x48, 0x65, 0x6c, 0x6c, 0x6f }
+    pub const C_ARRAY: HexConfig = HexConfig {
+        byte_prefix: "0x",
+        separator: ", ",
+        open: "{ ",
+        close: " }",
+        ..HexConfig::PLAIN
+    };
+
+    pub fn uppercase(mut self, uppercase: bool) -> HexConfig {
+        self.uppercase = uppercase;
+        self
+    }
+
+    pub fn byte_prefix(mut self, byte_prefix: &'static str) -> HexConfig {
+        self.byte_prefix = byte_prefix;
+        self
+    }
+
+    pub fn separator(mut self, separator: &'static str) -> HexConfig {
+        self.separator = separator;
+        self
+    }
+
+    pub fn encode(&self, buffer: &[u8]) -> Vec<u8> {
+        let chars = if self.uppercase {
+            UPPER_CHARS
+        } else {
+            LOWER_CHARS
+        };
+
+        let mut encoded = self.open.as_bytes().to_vec();
+        for (i, byte) in buffer.iter().enumerate() {
+            if i > 0 {
+                encoded.extend_from_slice(self.separator.as_bytes());
+            }
+            encoded.extend_from_slice(self.byte_prefix.as_bytes());
+            encoded.push(chars[(byte >> 4) as usize]);
+            encoded.push(chars[(byte & 0x0f) as usize]);
+        }
+        encoded.extend_from_slice(self.close.as_bytes());
+
+        encoded
+    }
+}
+
+impl Default for HexConfig {
+    fn default() -> Self {
+        HexConfig::PLAIN
+    }
+}
+
+// reads back anything HexConfig writes, and most hex people paste: bytes or
+// runs of bytes separated by whitespace, colons, commas or semicolons, each
+// maybe with a 0x or \x in front, maybe all in braces or brackets
+pub fn decode_loose(buffer: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = vec![];
+
+    let tokens = buffer.split(|c| c.is_ascii_whitespace() || b":,;{}[]\\".contains(c));
+    for token in tokens.filter(|token| !token.is_empty()) {
+        let digits = token
+            .strip_prefix(b"0x")
+            .or_else(|| token.strip_prefix(b"0X"))
+            // what's left of \x once the backslash has been split off
+            .or_else(|| token.strip_prefix(b"x"))
+            .unwrap_or(token);
+        decoded.extend(decode(digits)?);
+    }
+
+    Ok(decoded)
+}
+
+// the hex column of one dump line, from just after its offset
+fn dump_hex_column(line: &str) -> &str {
+    if let Some(column) = line.strip_prefix(':') {
+        // xxd: the text column comes after a gap of two spaces or more
+        let column = column.trim_start_matches(' ');
+        column.split("  ").next().unwrap_or("")
+    } else {
+        // hexdump -C puts the text between bars. the crate's own grids put it
+        // on a line of its own, which never starts with an offset
+        line.split('|').next().unwrap_or("")
+    }
+}
+
+// reads xxd, hexdump -C or ByteBufferDisplayFormat::Grid and GridAscii back
+// into bytes. each line starts with its offset, which has to follow on from
+// the line before, except after a * where hexdump squeezed out repeats of
+// the line before it
+pub fn decode_dump(dump: &str) -> Result<Vec<u8>> {
+    let mut decoded: Vec<u8> = vec![];
+    let mut previous: Vec<u8> = vec![];
+    let mut squeezed = false;
+
+    for line in dump.lines() {
+        if line.trim() == "*" {
+            squeezed = true;
+            continue;
+        }
+        let offset_len = line
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(line.len());
+        // blank lines, and the text lines under GridAscii
+        if offset_len == 0 {
+            continue;
+        }
+        let (offset, rest) = line.split_at(offset_len);
+        let offset = usize::from_str_radix(offset, 16)
+            .map_err(|_| error("dump has an offset that doesn't fit"))?;
+
+        if squeezed {
+            if previous.is_empty() || offset < decoded.len() {
+                return Err(error("dump squeezes out lines it can't restore").into());
+            }
+            while decoded.len() < offset {
+                let take = previous.len().min(offset - decoded.len());
+                decoded.extend_from_slice(&previous[..take]);
+            }
+            squeezed = false;
+        }
+        if offset != decoded.len() {
+            return Err(error(&format!("dump line at offset {:x} is out of place", offset)).into());
+        }
+
+        let hex: Vec<u8> = dump_hex_column(rest)
+            .bytes()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+        previous = decode(&hex)?;
+        decoded.extend_from_slice(&previous);
+    }
+
+    Ok(decoded)
+}
+
+// encodes everything written to it straight into inner
+pub struct HexEncoder<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> HexEncoder<W> {
+    pub fn new(inner: W) -> HexEncoder<W> {
+        HexEncoder { inner }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for HexEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(&encode(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// decodes whatever inner gives it, skipping whitespace the way the line
+// wrapped files this gets used on need. with no way to know the length up
+// front, a digit left over at the end is an error rather than padded out
+pub struct HexDecoder<R: Read> {
+    inner: R,
+    // the upper nibble, when a chunk ended between the two digits of a byte
+    pending: Option<u8>,
+}
+
+impl<R: Read> HexDecoder<R> {
+    pub fn new(inner: R) -> HexDecoder<R> {
+        HexDecoder {
+            inner,
+            pending: None,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for HexDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // two digits per byte, plus one that may finish a pending byte
+        let mut chunk = vec![0u8; buf.len() * 2 - self.pending.is_some() as usize];
+        loop {
+            let read = self.inner.read(&mut chunk)?;
+            if read == 0 {
+                if self.pending.is_some() {
+                    return Err(invalid_data(error("buffer has an odd length").into()));
+                }
+                return Ok(0);
+            }
+
+            let mut written = 0;
+            for byte in chunk[..read]
+                .iter()
+                .filter(|byte| !byte.is_ascii_whitespace())
+            {
+                let value = nibble(*byte).map_err(|e| invalid_data(e.into()))?;
+                match self.pending.take() {
+                    Some(upper) => {
+                        buf[written] = (upper << 4) | value;
+                        written += 1;
+                    }
+                    None => self.pending = Some(value),
+                }
+            }
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hex_streams() {
+    let data: Vec<u8> = (0..=255).collect();
+
+    let mut encoder = HexEncoder::new(vec![]);
+    for chunk in data.chunks(7) {
+        encoder.write_all(chunk).unwrap();
+    }
+    let encoded = encoder.finish().unwrap();
+    assert_eq!(encoded, encode(&data));
+
+    let mut decoded = vec![];
+    let wrapped = [&encoded[..33], b"\n", &encoded[33..]].concat();
+    HexDecoder::new(&wrapped[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+
+    let mut decoded = vec![];
+    assert!(HexDecoder::new(&b"abc"[..])
+        .read_to_end(&mut decoded)
+        .is_err());
+    assert!(HexDecoder::new(&b""[..]).read_to_end(&mut decoded).is_ok());
+}
+
+#[test]
+fn test_hex_formats() {
+    assert_eq!(decode(b"DEADbeef").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+    assert!(decode(b"abc").is_err());
+
+    let bytes = b"Hi!\xff";
+    assert_eq!(HexConfig::COLONS.encode(bytes), b"48:69:21:ff".to_vec());
+    assert_eq!(
+        HexConfig::C_ARRAY.uppercase(true).encode(bytes),
+        b"{ 0x48, 0x69, 0x21, 0xFF }".to_vec()
+    );
+    for config in [
+        HexConfig::PLAIN,
+        HexConfig::COLONS,
+        HexConfig::SPACED,
+        HexConfig::PREFIXED,
+        HexConfig::C_ARRAY,
+    ] {
+        assert_eq!(decode_loose(&config.encode(bytes)).unwrap(), bytes.to_vec());
+    }
+    assert_eq!(decode_loose(b"\\x48\\x69").unwrap(), b"Hi".to_vec());
+}
+
+#[test]
+fn test_decode_dump() {
+    use crate::byte_buffer::{ByteBuffer, ByteBufferDisplayFormat};
+
+    let xxd = "00000000: 6361 6665 2062 6162 6520 6361 6665 2062  cafe babe cafe b\n\
+               00000010: 6162 650a                                abe.\n";
+    assert_eq!(decode_dump(xxd).unwrap(), b"cafe babe cafe babe\n".to_vec());
+
+    let hexdump =
+        "00000000  61 61 61 61 61 61 61 61  61 61 61 61 61 61 61 61  |aaaaaaaaaaaaaaaa|\n\
+                   *\n\
+                   00000030  62 0a                                             |b.|\n\
+                   00000032\n";
+    let mut expected = vec![b'a'; 48];
+    expected.extend_from_slice(b"b\n");
+    assert_eq!(decode_dump(hexdump).unwrap(), expected);
 
-    for (i, byte) in buffer.iter().enumerate() {
-        encoded[i * 2] = HEX_CHAR_INDEXES[(byte >> 4) as usize] as u8;
-        encoded[(i * 2) + 1] = HEX_CHAR_INDEXES[(byte & 0x0F) as usize] as u8;
+    let bytes: Vec<u8> = (0..40).collect();
+    for format in [
+        ByteBufferDisplayFormat::Grid,
+        ByteBufferDisplayFormat::GridAscii,
+    ] {
+        assert_eq!(decode_dump(&bytes.to_string(format)).unwrap(), bytes);
     }
 
-    encoded
+    assert!(decode_dump("00000010: 6162\n").is_err());
 }
@@ -0,0 +1,242 @@
+use crate::error::{Error, Result};
+use crate::percent;
+use std::borrow::Cow;
+
+// the characters between pairs, and between a key and its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Separators {
+    pub pair: char,
+    pub key: char,
+}
+
+impl Separators {
+    // foo=bar&baz=qux, as in challenge 13's profiles
+    pub const QUERY: Separators = Separators {
+        pair: '&',
+        key: '=',
+    };
+    // comment1=cooking%20MCs;userdata=..., as in the CBC bitflipping challenge
+    pub const COMMENT: Separators = Separators {
+        pair: ';',
+        key: '=',
+    };
+}
+
+// what to do with a key that turns up more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicates {
+    First,
+    Last,
+    Error,
+    All,
+}
+
+// pairs in the order they were parsed or inserted. parsed keys and values
+// borrow from the input unless unescaping them changed them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KvMap<'a> {
+    pairs: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+impl<'a> KvMap<'a> {
+    pub fn new() -> KvMap<'a> {
+        KvMap { pairs: vec![] }
+    }
+
+    // adds to the end, whether or not key is already there
+    pub fn push(&mut self, key: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+
+    // the first value for key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_ref())
+    }
+
+    pub fn get_all<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'b str> + 'b {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_ref())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn into_owned(self) -> KvMap<'static> {
+        KvMap {
+            pairs: self
+                .pairs
+                .into_iter()
+                .map(|(k, v)| (Cow::Owned(k.into_owned()), Cow::Owned(v.into_owned())))
+                .collect(),
+        }
+    }
+}
+
+// parses and serializes key-value strings. by default it's strict the way
+// kv_string_to_map always was: a separator inside a key or value is an
+// error rather than something to escape, and repeated keys are all kept
+#[derive(Debug, Clone, Copy)]
+pub struct KvCodec {
+    separators: Separators,
+    duplicates: Duplicates,
+    escaping: bool,
+}
+
+impl KvCodec {
+    pub fn new(separators: Separators) -> KvCodec {
+        KvCodec {
+            separators,
+            duplicates: Duplicates::All,
+            escaping: false,
+        }
+    }
+
+    pub fn duplicates(mut self, duplicates: Duplicates) -> KvCodec {
+        self.duplicates = duplicates;
+        self
+    }
+
+    // percent-escapes separators, '%' and anything unprintable on encode,
+    // and unescapes on decode
+    pub fn escaping(mut self, escaping: bool) -> KvCodec {
+        self.escaping = escaping;
+        self
+    }
+
+    fn unescape<'a>(&self, field: &'a str) -> Result<Cow<'a, str>> {
+        if !self.escaping || !field.contains('%') {
+            return Ok(Cow::Borrowed(field));
+        }
+        String::from_utf8(percent::decode(field.as_bytes()))
+            .map(Cow::Owned)
+            .map_err(|_| Error::Parse(String::from("Invalid utf8")))
+    }
+
+    fn escape<'a>(&self, field: &'a str) -> Result<Cow<'a, str>> {
+        let Separators { pair, key } = self.separators;
+        if self.escaping {
+            let reserved = format!("{}{}", pair, key).into_bytes();
+            let escaped = percent::encode(field.as_bytes(), &reserved);
+            return Ok(Cow::Owned(String::from_utf8_lossy(&escaped).into_owned()));
+        }
+
+        if field.contains(pair) || field.contains(key) {
+            return Err(Error::Parse(String::from(
+                "Invalid characters in map items",
+            )));
+        }
+        Ok(Cow::Borrowed(field))
+    }
+
+    pub fn decode<'a>(&self, input: &'a str) -> Result<KvMap<'a>> {
+        let mut map = KvMap::new();
+        if input.is_empty() {
+            return Ok(map);
+        }
+
+        let mut pairs = input.split(self.separators.pair).peekable();
+        while let Some(pair) = pairs.next() {
+            let (key, value) = match pair.split_once(self.separators.key) {
+                Some((key, value)) if !key.is_empty() && !value.contains(self.separators.key) => {
+                    (key, value)
+                }
+                None if pairs.peek().is_none() => {
+                    return Err(Error::Parse(String::from("Unexpected end of input")))
+                }
+                _ => return Err(Error::Parse(String::from("Unexpected character"))),
+            };
+            let (key, value) = (self.unescape(key)?, self.unescape(value)?);
+
+            match self.duplicates {
+                Duplicates::All => {}
+                _ if !map.contains_key(&key) => {}
+                Duplicates::First => continue,
+                Duplicates::Last => map.pairs.retain(|(k, _)| *k != key),
+                Duplicates::Error => {
+                    return Err(Error::Parse(format!("Repeated key {}", key)));
+                }
+            }
+            map.pairs.push((key, value));
+        }
+
+        Ok(map)
+    }
+
+    pub fn encode(&self, map: &KvMap) -> Result<String> {
+        let mut pairs = vec![];
+        for (key, value) in map.iter() {
+            if key.is_empty() {
+                return Err(Error::Parse(String::from("Empty key in map")));
+            }
+            pairs.push(format!(
+                "{}{}{}",
+                self.escape(key)?,
+                self.separators.key,
+                self.escape(value)?
+            ));
+        }
+
+        Ok(pairs.join(&self.separators.pair.to_string()))
+    }
+}
+
+#[test]
+fn test_kv_codec() {
+    let codec = KvCodec::new(Separators::QUERY);
+    let map = codec.decode("foo=bar&baz=qux&foo=again").unwrap();
+    assert_eq!(
+        map.iter().collect::<Vec<_>>(),
+        vec![("foo", "bar"), ("baz", "qux"), ("foo", "again")]
+    );
+    assert_eq!(map.get("foo"), Some("bar"));
+    assert_eq!(map.get_all("foo").collect::<Vec<_>>(), vec!["bar", "again"]);
+    assert_eq!(codec.encode(&map).unwrap(), "foo=bar&baz=qux&foo=again");
+    assert!(matches!(map.pairs[0].1, Cow::Borrowed(_)));
+
+    let first = codec.duplicates(Duplicates::First);
+    assert_eq!(first.decode("a=1&b=2&a=3").unwrap().get("a"), Some("1"));
+    let last = codec.duplicates(Duplicates::Last);
+    assert_eq!(
+        last.encode(&last.decode("a=1&b=2&a=3").unwrap()).unwrap(),
+        "b=2&a=3"
+    );
+    assert!(codec
+        .duplicates(Duplicates::Error)
+        .decode("a=1&b=2&a=3")
+        .is_err());
+
+    let mut map = KvMap::new();
+    map.push("userdata", ";admin=true");
+    assert_eq!(
+        KvCodec::new(Separators::COMMENT)
+            .encode(&map)
+            .unwrap_err()
+            .to_string(),
+        "Invalid characters in map items"
+    );
+    let escaping = KvCodec::new(Separators::COMMENT).escaping(true);
+    let encoded = escaping.encode(&map).unwrap();
+    assert_eq!(encoded, "userdata=%3Badmin%3Dtrue");
+    assert_eq!(escaping.decode(&encoded).unwrap(), map);
+
+    assert_eq!(codec.decode("").unwrap().len(), 0);
+    assert!(codec.decode("=bar").is_err());
+}
@@ -0,0 +1,644 @@
+use crate::bignum::BigInt;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// exact fractions, kept in lowest terms with a positive denominator, so the
+// Gram-Schmidt coefficients LLL works with never lose precision
+#[derive(Clone, PartialEq, Eq)]
+pub struct Rational {
+    num: BigInt,
+    den: BigInt,
+}
+
+impl Rational {
+    // panics on a zero denominator, like integer division
+    pub fn new(num: BigInt, den: BigInt) -> Rational {
+        assert!(!den.is_zero(), "zero denominator");
+
+        let divisor = num.gcd(&den);
+        let (mut num, mut den) = if divisor.is_zero() {
+            (num, den)
+        } else {
+            (&num / &divisor, &den / &divisor)
+        };
+        if den.is_negative() {
+            num = -num;
+            den = -den;
+        }
+        Rational { num, den }
+    }
+
+    pub fn zero() -> Rational {
+        Rational::from(BigInt::zero())
+    }
+
+    pub fn one() -> Rational {
+        Rational::from(BigInt::one())
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.num
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+
+    pub fn abs(&self) -> Rational {
+        Rational {
+            num: self.num.abs(),
+            den: self.den.clone(),
+        }
+    }
+
+    // rounded towards negative infinity
+    pub fn floor(&self) -> BigInt {
+        let quotient = &self.num / &self.den;
+        if self.num.is_negative() && !(&self.num % &self.den).is_zero() {
+            &quotient - &BigInt::one()
+        } else {
+            quotient
+        }
+    }
+
+    // the nearest integer, rounding halves up
+    pub fn round(&self) -> BigInt {
+        (self + &Rational::new(BigInt::one(), BigInt::from(2u32))).floor()
+    }
+
+    // the nearest double, near enough, even when both halves are too big for
+    // one. each half keeps only its top bits, and what was shifted off them
+    // goes back on the quotient, so it only overflows when the quotient itself
+    // is too big for a double
+    pub fn to_f64(&self) -> f64 {
+        let (num, num_shift) = top_bits(&self.num);
+        let (den, den_shift) = top_bits(&self.den);
+        let shift = (num_shift as i64 - den_shift as i64).clamp(-4096, 4096) as i32;
+        // in two halves, since a quotient near the edge of the range can have
+        // a shift that is past it
+        num / den * 2f64.powi(shift / 2) * 2f64.powi(shift - shift / 2)
+    }
+}
+
+// the top 63 bits of n as a double, and how far they were shifted down
+fn top_bits(n: &BigInt) -> (f64, usize) {
+    let shift = n.num_bits().saturating_sub(63);
+    let magnitude = (n.abs() >> shift).to_u64().unwrap() as f64;
+    if n.is_negative() {
+        (-magnitude, shift)
+    } else {
+        (magnitude, shift)
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(n: BigInt) -> Rational {
+        Rational {
+            num: n,
+            den: BigInt::one(),
+        }
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Rational {
+        Rational::from(BigInt::from(n))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den.is_one() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl fmt::Debug for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (&self.num * &other.den).cmp(&(&other.num * &self.den))
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Rational {
+        Rational::new(
+            &(&self.num * &other.den) + &(&other.num * &self.den),
+            &self.den * &other.den,
+        )
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Rational {
+        Rational::new(
+            &(&self.num * &other.den) - &(&other.num * &self.den),
+            &self.den * &other.den,
+        )
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.num, &self.den * &other.den)
+    }
+}
+
+impl Div for &Rational {
+    type Output = Rational;
+
+    fn div(self, other: &Rational) -> Rational {
+        Rational::new(&self.num * &other.den, &self.den * &other.num)
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            num: -&self.num,
+            den: self.den.clone(),
+        }
+    }
+}
+
+fn dot(a: &[Rational], b: &[Rational]) -> Rational {
+    a.iter()
+        .zip(b)
+        .fold(Rational::zero(), |sum, (x, y)| &sum + &(x * y))
+}
+
+// an integer row vector
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vector(pub Vec<BigInt>);
+
+impl Vector {
+    pub fn zero(len: usize) -> Vector {
+        Vector(vec![BigInt::zero(); len])
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn dot(&self, other: &Vector) -> BigInt {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .fold(BigInt::zero(), |sum, (x, y)| &sum + &(x * y))
+    }
+
+    pub fn norm_squared(&self) -> BigInt {
+        self.dot(self)
+    }
+
+    pub fn scaled(&self, k: &BigInt) -> Vector {
+        Vector(self.0.iter().map(|x| x * k).collect())
+    }
+
+    fn to_rational(&self) -> Vec<Rational> {
+        self.0.iter().cloned().map(Rational::from).collect()
+    }
+}
+
+impl Add for &Vector {
+    type Output = Vector;
+
+    fn add(self, other: &Vector) -> Vector {
+        Vector(self.0.iter().zip(&other.0).map(|(x, y)| x + y).collect())
+    }
+}
+
+impl Sub for &Vector {
+    type Output = Vector;
+
+    fn sub(self, other: &Vector) -> Vector {
+        Vector(self.0.iter().zip(&other.0).map(|(x, y)| x - y).collect())
+    }
+}
+
+// the Gram-Schmidt orthogonalisation of a basis: the orthogonal rows b*_i,
+// the coefficients mu[i][j] = <b_i, b*_j> / <b*_j, b*_j>, and the squared
+// lengths <b*_i, b*_i>. rows that depend on earlier ones get a zero b*_i
+#[derive(Clone, Debug)]
+pub struct GramSchmidt {
+    pub orthogonal: Vec<Vec<Rational>>,
+    pub mu: Vec<Vec<Rational>>,
+    pub norms: Vec<Rational>,
+}
+
+// a lattice basis, one integer row per basis vector
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matrix {
+    pub rows: Vec<Vector>,
+}
+
+impl From<Vec<Vec<BigInt>>> for Matrix {
+    fn from(rows: Vec<Vec<BigInt>>) -> Matrix {
+        Matrix {
+            rows: rows.into_iter().map(Vector).collect(),
+        }
+    }
+}
+
+impl Matrix {
+    pub fn identity(n: usize) -> Matrix {
+        let mut rows = vec![Vector::zero(n); n];
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.0[i] = BigInt::one();
+        }
+        Matrix { rows }
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.rows.first().map_or(0, Vector::len)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        Matrix {
+            rows: (0..self.ncols())
+                .map(|j| Vector(self.rows.iter().map(|row| row.0[j].clone()).collect()))
+                .collect(),
+        }
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Matrix {
+        let columns = other.transpose();
+        Matrix {
+            rows: self
+                .rows
+                .iter()
+                .map(|row| Vector(columns.rows.iter().map(|column| row.dot(column)).collect()))
+                .collect(),
+        }
+    }
+
+    pub fn gram_schmidt(&self) -> GramSchmidt {
+        let n = self.nrows();
+        let mut orthogonal: Vec<Vec<Rational>> = vec![];
+        let mut mu = vec![vec![Rational::zero(); n]; n];
+        let mut norms: Vec<Rational> = vec![];
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let row = row.to_rational();
+            let mut projected = row.clone();
+            for j in 0..i {
+                if norms[j].is_zero() {
+                    continue;
+                }
+                mu[i][j] = &dot(&row, &orthogonal[j]) / &norms[j];
+                for (x, y) in projected.iter_mut().zip(&orthogonal[j]) {
+                    *x = &*x - &(&mu[i][j] * y);
+                }
+            }
+            norms.push(dot(&projected, &projected));
+            orthogonal.push(projected);
+        }
+
+        GramSchmidt {
+            orthogonal,
+            mu,
+            norms,
+        }
+    }
+
+    // the determinant of the Gram matrix, the square of the lattice's volume,
+    // which reduction never changes
+    pub fn gram_determinant(&self) -> Rational {
+        self.gram_schmidt()
+            .norms
+            .iter()
+            .fold(Rational::one(), |product, norm| &product * norm)
+    }
+
+    // LLL with the usual delta = 99/100
+    pub fn lll(&self) -> Matrix {
+        self.lll_with_delta(&Rational::new(BigInt::from(99u32), BigInt::from(100u32)))
+    }
+
+    // LLL reduction of a basis of linearly independent rows, for delta in
+    // (1/4, 1]. the result spans the same lattice, with short, nearly
+    // orthogonal rows, the first of which is within a factor of about
+    // (1 / (delta - 1/4))^(n/2) of the shortest vector. delta closer to 1
+    // reduces further, for more work
+    pub fn lll_with_delta(&self, delta: &Rational) -> Matrix {
+        assert!(
+            *delta > Rational::new(BigInt::one(), BigInt::from(4u32)) && *delta <= Rational::one(),
+            "delta must be in (1/4, 1]"
+        );
+
+        let mut basis = self.clone();
+        let n = basis.nrows();
+        let GramSchmidt {
+            mut mu, mut norms, ..
+        } = basis.gram_schmidt();
+
+        let mut k = 1;
+        while k < n {
+            // size reduction, which leaves the orthogonalised rows alone
+            for j in (0..k).rev() {
+                let q = mu[k][j].round();
+                if q.is_zero() {
+                    continue;
+                }
+                basis.rows[k] = &basis.rows[k] - &basis.rows[j].scaled(&q);
+
+                let q = Rational::from(q);
+                let (above, below) = mu.split_at_mut(k);
+                for (x, y) in below[0][..j].iter_mut().zip(&above[j][..j]) {
+                    *x = &*x - &(&q * y);
+                }
+                mu[k][j] = &mu[k][j] - &q;
+            }
+
+            // the Lovasz condition
+            let mu_squared = &mu[k][k - 1] * &mu[k][k - 1];
+            if norms[k] >= &(delta - &mu_squared) * &norms[k - 1] {
+                k += 1;
+            } else {
+                basis.swap_rows(&mut mu, &mut norms, k);
+                k = (k - 1).max(1);
+            }
+        }
+
+        basis
+    }
+
+    // swaps rows k - 1 and k, updating the Gram-Schmidt data in place rather
+    // than starting over, as in Cohen's algorithm 2.6.3
+    fn swap_rows(&mut self, mu: &mut [Vec<Rational>], norms: &mut [Rational], k: usize) {
+        self.rows.swap(k, k - 1);
+
+        let m = mu[k][k - 1].clone();
+        let norm = &norms[k] + &(&(&m * &m) * &norms[k - 1]);
+        mu[k][k - 1] = &(&m * &norms[k - 1]) / &norm;
+        norms[k] = &(&norms[k - 1] * &norms[k]) / &norm;
+        norms[k - 1] = norm;
+
+        let (above, below) = mu.split_at_mut(k);
+        above[k - 1][..(k - 1)].swap_with_slice(&mut below[0][..(k - 1)]);
+        for i in (k + 1)..self.nrows() {
+            let t = mu[i][k].clone();
+            mu[i][k] = &mu[i][k - 1] - &(&m * &t);
+            mu[i][k - 1] = &t + &(&mu[k][k - 1] * &mu[i][k]);
+        }
+    }
+
+    // a cut down BKZ: after LLL, each block of block_size rows is searched
+    // exhaustively for the shortest vector of its projection orthogonal to
+    // the rows before it. if that beats the block's first row by more than
+    // delta, it goes in at the front of the block, and the basis is LLL
+    // reduced again. tours over every block repeat until none improves. the
+    // search runs in floating point, which is fine for blocks of up to 20 or
+    // so rows, and only affects which vectors get tried. the norms are taken
+    // relative to the block's first, so that entries far too big for a double
+    // still give finite ones
+    pub fn bkz(&self, block_size: usize, delta: &Rational) -> Matrix {
+        let mut basis = self.lll_with_delta(delta);
+        let n = basis.nrows();
+        let float_delta = delta.to_f64();
+
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for k in 0..n.saturating_sub(1) {
+                let end = (k + block_size).min(n);
+                let gs = basis.gram_schmidt();
+                let mu: Vec<Vec<f64>> = gs
+                    .mu
+                    .iter()
+                    .map(|row| row.iter().map(Rational::to_f64).collect())
+                    .collect();
+                let norms: Vec<f64> = gs
+                    .norms
+                    .iter()
+                    .map(|norm| (norm / &gs.norms[k]).to_f64())
+                    .collect();
+
+                if let Some(coefficients) = shortest_projection(&mu, &norms, k, end, float_delta) {
+                    basis.insert_combination(k, &coefficients);
+                    basis = basis.lll_with_delta(delta);
+                    improved = true;
+                }
+            }
+        }
+
+        basis
+    }
+
+    // replaces rows k.. with a basis of the same sublattice whose first row
+    // is the combination sum coefficients[i] * row[k + i], which must have
+    // coprime coefficients. rows are combined euclid style, which keeps
+    // that sum fixed, until only one coefficient is left, and it's then 1 or -1
+    fn insert_combination(&mut self, k: usize, coefficients: &[BigInt]) {
+        let mut coefficients = coefficients.to_vec();
+        loop {
+            let nonzero: Vec<usize> = (0..coefficients.len())
+                .filter(|i| !coefficients[*i].is_zero())
+                .collect();
+            let smallest = *nonzero
+                .iter()
+                .min_by_key(|i| coefficients[**i].abs())
+                .unwrap();
+            if nonzero.len() == 1 {
+                if coefficients[smallest].is_negative() {
+                    self.rows[k + smallest] = self.rows[k + smallest].scaled(&BigInt::from(-1i64));
+                }
+                self.rows[k..=(k + smallest)].rotate_right(1);
+                return;
+            }
+
+            for i in nonzero {
+                if i == smallest {
+                    continue;
+                }
+                let q = &coefficients[i] / &coefficients[smallest];
+                coefficients[i] = &coefficients[i] - &(&q * &coefficients[smallest]);
+                self.rows[k + smallest] = &self.rows[k + smallest] + &self.rows[k + i].scaled(&q);
+            }
+        }
+    }
+}
+
+// enumerates the integer combinations of rows start..end whose projections
+// orthogonal to the rows before start are shorter than radius, and returns
+// the coefficients of the shortest, if any. levels are fixed from the last
+// row down, each within the range the remaining radius allows
+fn shortest_projection(
+    mu: &[Vec<f64>],
+    norms: &[f64],
+    start: usize,
+    end: usize,
+    radius: f64,
+) -> Option<Vec<BigInt>> {
+    fn search(
+        mu: &[Vec<f64>],
+        norms: &[f64],
+        start: usize,
+        level: usize,
+        partial: f64,
+        x: &mut Vec<i64>,
+        best: &mut (f64, Option<Vec<i64>>),
+    ) {
+        let i = start + level;
+        let center: f64 = -(level + 1..x.len())
+            .map(|j| x[j] as f64 * mu[start + j][i])
+            .sum::<f64>();
+        let width = ((best.0 - partial) / norms[i]).max(0.0).sqrt();
+
+        for value in (center - width).ceil() as i64..=(center + width).floor() as i64 {
+            let norm = partial + (value as f64 - center).powi(2) * norms[i];
+            if norm >= best.0 {
+                continue;
+            }
+            x[level] = value;
+            if level > 0 {
+                search(mu, norms, start, level - 1, norm, x, best);
+            } else if x.iter().any(|v| *v != 0) {
+                *best = (norm, Some(x.clone()));
+            }
+        }
+        x[level] = 0;
+    }
+
+    let mut x = vec![0i64; end - start];
+    let mut best = (radius, None);
+    search(mu, norms, start, end - start - 1, 0.0, &mut x, &mut best);
+
+    best.1.map(|x| x.into_iter().map(BigInt::from).collect())
+}
+
+#[cfg(test)]
+fn matrix_from_i64(rows: &[&[i64]]) -> Matrix {
+    Matrix::from(
+        rows.iter()
+            .map(|row| row.iter().map(|x| BigInt::from(*x)).collect())
+            .collect::<Vec<Vec<BigInt>>>(),
+    )
+}
+
+#[test]
+fn test_lll() {
+    let basis = matrix_from_i64(&[&[1, 1, 1], &[-1, 0, 2], &[3, 5, 6]]);
+
+    // any reduced basis of this lattice has rows of squared length 1, 2 and 5
+    let reduced = basis.lll();
+    let norms: Vec<u64> = reduced
+        .rows
+        .iter()
+        .map(|row| row.norm_squared().to_u64().unwrap())
+        .collect();
+
+    assert_eq!(
+        reduced.rows[0],
+        Vector([0u32, 1, 0].map(BigInt::from).to_vec())
+    );
+    assert_eq!(norms, [1, 2, 5]);
+    assert_eq!(reduced.gram_determinant(), basis.gram_determinant());
+
+    let gs = reduced.gram_schmidt();
+    assert!(dot(&gs.orthogonal[0], &gs.orthogonal[2]).is_zero());
+    assert!(gs.mu[2][1].abs() <= Rational::new(BigInt::one(), BigInt::from(2u32)));
+}
+
+#[test]
+fn test_bkz() {
+    // a knapsack style lattice whose short vectors LLL with a weak delta
+    // tends to miss
+    let basis = matrix_from_i64(&[
+        &[1, 0, 0, 0, 0, 0, 0, 0, 575_937],
+        &[0, 1, 0, 0, 0, 0, 0, 0, 247_454],
+        &[0, 0, 1, 0, 0, 0, 0, 0, 830_642],
+        &[0, 0, 0, 1, 0, 0, 0, 0, 137_474],
+        &[0, 0, 0, 0, 1, 0, 0, 0, 894_563],
+        &[0, 0, 0, 0, 0, 1, 0, 0, 482_733],
+        &[0, 0, 0, 0, 0, 0, 1, 0, 362_017],
+        &[0, 0, 0, 0, 0, 0, 0, 1, 991_248],
+        &[0, 0, 0, 0, 0, 0, 0, 0, 1_000_003],
+    ]);
+    let delta = Rational::new(BigInt::from(3u32), BigInt::from(10u32));
+
+    let lll = basis.lll_with_delta(&delta);
+    let bkz = basis.bkz(4, &delta);
+
+    assert_eq!(bkz.gram_determinant(), basis.gram_determinant());
+    assert!(bkz.rows[0].norm_squared() <= lll.rows[0].norm_squared());
+
+    // with blocks as big as the basis, the first row is a shortest vector
+    let full = basis.bkz(9, &delta);
+    let gs = full.gram_schmidt();
+    let mu: Vec<Vec<f64>> = gs
+        .mu
+        .iter()
+        .map(|row| row.iter().map(Rational::to_f64).collect())
+        .collect();
+    let norms: Vec<f64> = gs.norms.iter().map(Rational::to_f64).collect();
+    let first = full.rows[0].norm_squared().to_u64().unwrap() as f64;
+    assert!(shortest_projection(&mu, &norms, 0, 9, first * 0.999).is_none());
+
+    // a lattice with a known shortest vector, scaled up to entries of around
+    // 2000 bits, whose norms are far past what a double can hold
+    let scale = BigInt::one() << 2000;
+    let small = matrix_from_i64(&[&[1, 1, 1], &[-1, 0, 2], &[3, 5, 6]]);
+    let huge = Matrix::from(
+        small
+            .rows
+            .iter()
+            .map(|row| row.scaled(&scale).0)
+            .collect::<Vec<Vec<BigInt>>>(),
+    );
+    let strong = Rational::new(BigInt::from(99u32), BigInt::from(100u32));
+    let reduced = huge.bkz(3, &strong);
+    assert_eq!(reduced.rows[0].norm_squared(), BigInt::one() << 4000);
+    for (row, small_row) in reduced.rows.iter().zip(small.bkz(3, &strong).rows.iter()) {
+        assert_eq!(row.norm_squared(), small_row.norm_squared() << 4000);
+    }
+}
+
+#[test]
+fn test_rational_to_f64() {
+    let big = BigInt::one() << 2000;
+    let close_to_two = Rational::new(&big + &BigInt::one(), BigInt::one() << 1999);
+    assert_eq!(close_to_two.to_f64(), 2.0);
+    assert_eq!((-&close_to_two).to_f64(), -2.0);
+    assert_eq!(
+        Rational::new(BigInt::from(3u32), BigInt::from(4u32)).to_f64(),
+        0.75
+    );
+    assert_eq!(Rational::from(big.clone()).to_f64(), f64::INFINITY);
+    assert_eq!(Rational::new(BigInt::one(), big).to_f64(), 0.0);
+}
@@ -1,7 +1,31 @@
+pub mod alphabet;
+pub mod ascii85;
+pub mod base32;
+pub mod base58;
 pub mod base64;
+pub mod bignum;
+pub mod bits;
 pub mod byte_buffer;
+pub mod challenges;
+pub mod crib;
+pub mod cut_and_paste;
 pub mod cypher;
+pub mod deflate;
+pub mod detect;
+pub mod dlog;
+pub mod ec;
+pub mod ecdh;
+pub mod ecdsa;
+pub mod error;
 pub mod hex;
+pub mod kv;
+pub mod lattice;
+pub mod md4;
+pub mod md4_collision;
+pub mod md_hash;
+pub mod percent;
+pub mod session;
 pub mod utils;
 
 pub use byte_buffer::*;
+pub use error::{Error, Result};
@@ -0,0 +1,148 @@
+use crate::error::{Error, Result};
+
+pub const BLOCK_SIZE: usize = 64;
+pub const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+const ROUND_2_CONSTANT: u32 = 0x5a827999;
+const ROUND_3_CONSTANT: u32 = 0x6ed9eba1;
+
+// which message word each of the 48 steps adds in, and how far it rotates
+pub const WORD_ORDER: [usize; 48] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+    0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15, //
+    0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15,
+];
+pub const SHIFTS: [u32; 12] = [3, 7, 11, 19, 3, 5, 9, 13, 3, 9, 11, 15];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+pub fn shift_for_step(step: usize) -> u32 {
+    SHIFTS[(step / 16) * 4 + step % 4]
+}
+
+// everything a step adds to the state four steps back before rotating, other
+// than the message word
+pub fn step_addend(step: usize, prev: u32, prev2: u32, prev3: u32) -> u32 {
+    match step / 16 {
+        0 => f(prev, prev2, prev3),
+        1 => g(prev, prev2, prev3).wrapping_add(ROUND_2_CONSTANT),
+        _ => h(prev, prev2, prev3).wrapping_add(ROUND_3_CONSTANT),
+    }
+}
+
+fn words(block: &[u8; BLOCK_SIZE]) -> [u32; 16] {
+    let (chunks, _) = block.as_chunks::<4>();
+    let mut words = [0u32; 16];
+    for (word, bytes) in words.iter_mut().zip(chunks) {
+        *word = u32::from_le_bytes(*bytes);
+    }
+    words
+}
+
+pub fn words_from_block(block: &[u8]) -> Result<[u32; 16]> {
+    let block = block
+        .try_into()
+        .map_err(|_| Error::LengthMismatch(block.len(), BLOCK_SIZE))?;
+    Ok(words(block))
+}
+
+pub fn block_from_words(words: &[u32; 16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+// the value written by each step, with the four initial state words in front
+// in the order they're overwritten: a0, d0, c0, b0, a1, d1, c1, b1, a2, ...
+pub fn trace(state: &[u32; 4], words: &[u32; 16]) -> [u32; 52] {
+    let mut q = [0u32; 52];
+    q[..4].copy_from_slice(&[state[0], state[3], state[2], state[1]]);
+
+    for step in 0..48 {
+        let i = step + 4;
+        q[i] = q[i - 4]
+            .wrapping_add(step_addend(step, q[i - 1], q[i - 2], q[i - 3]))
+            .wrapping_add(words[WORD_ORDER[step]])
+            .rotate_left(shift_for_step(step));
+    }
+
+    q
+}
+
+pub fn compress_words(state: &mut [u32; 4], words: &[u32; 16]) {
+    let q = trace(state, words);
+
+    state[0] = state[0].wrapping_add(q[48]);
+    state[1] = state[1].wrapping_add(q[51]);
+    state[2] = state[2].wrapping_add(q[50]);
+    state[3] = state[3].wrapping_add(q[49]);
+}
+
+// block has to be exactly one block long
+pub fn compress(state: &mut [u32; 4], block: &[u8]) -> Result<()> {
+    compress_words(state, &words_from_block(block)?);
+    Ok(())
+}
+
+// the padding appended to a message of total_len bytes: a 1 bit, zeros, and
+// the length in bits as a little endian u64
+pub fn pad(total_len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80u8];
+    while (total_len + padding.len()) % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        padding.push(0);
+    }
+    padding.extend_from_slice(&((total_len as u64).wrapping_mul(8)).to_le_bytes());
+
+    padding
+}
+
+pub fn digest(msg: &[u8]) -> Vec<u8> {
+    let padded = [msg, &pad(msg.len())[..]].concat();
+
+    // the padding always leaves whole blocks
+    let (blocks, _) = padded.as_chunks::<BLOCK_SIZE>();
+    let mut state = INITIAL_STATE;
+    for block in blocks {
+        compress_words(&mut state, &words(block));
+    }
+
+    state.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_md4_digest() {
+    use crate::hex;
+
+    assert_eq!(
+        hex::encode(&digest(b"")),
+        b"31d6cfe0d16ae931b73c59d7e0c089c0".to_vec()
+    );
+    assert_eq!(
+        hex::encode(&digest(b"abc")),
+        b"a448017aaf21d8525fc10ae87aa6729d".to_vec()
+    );
+    assert_eq!(
+        hex::encode(&digest(
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+        )),
+        b"e33b4ddc9c38f2199c3e7b164fcc0536".to_vec()
+    );
+
+    let mut state = INITIAL_STATE;
+    assert!(matches!(
+        compress(&mut state, &[0u8; 63]),
+        Err(Error::LengthMismatch(63, 64))
+    ));
+    assert!(words_from_block(&[0u8; 65]).is_err());
+    assert_eq!(state, INITIAL_STATE);
+    compress(&mut state, &[0u8; 64]).unwrap();
+    assert_ne!(state, INITIAL_STATE);
+}
@@ -0,0 +1,365 @@
+use crate::error::{Error, Result};
+use crate::md4;
+use rand::prelude::*;
+
+// a sufficient condition on one bit of a step's output, for the differential
+// from Wang et al.'s "Cryptanalysis of the Hash Functions MD4 and RIPEMD".
+// bits are numbered from 1 as in the paper, and Equal / NotEqual compare
+// against the same bit of the output some number of steps back
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    Zero(u32),
+    One(u32),
+    Equal(u32, usize),
+    NotEqual(u32, usize),
+}
+
+use Condition::*;
+
+// the conditions on each step's output, by step: a1 is step 0, d1 step 1,
+// c1 step 2, b1 step 3, a2 step 4, and so on through c6, then b9 and a10
+pub const CONDITIONS: [(usize, &[Condition]); 25] = [
+    (0, &[Equal(7, 1)]),
+    (1, &[Zero(7), Equal(8, 1), Equal(11, 1)]),
+    (2, &[One(7), One(8), Zero(11), Equal(26, 1)]),
+    (3, &[One(7), Zero(8), Zero(11), Zero(26)]),
+    (4, &[One(8), One(11), Zero(26), Equal(14, 1)]),
+    (
+        5,
+        &[
+            Zero(14),
+            Equal(19, 1),
+            Equal(20, 1),
+            Equal(21, 1),
+            Equal(22, 1),
+            One(26),
+        ],
+    ),
+    (
+        6,
+        &[
+            Equal(13, 1),
+            Zero(14),
+            Equal(15, 1),
+            Zero(19),
+            Zero(20),
+            One(21),
+            Zero(22),
+        ],
+    ),
+    (
+        7,
+        &[
+            One(13),
+            One(14),
+            Zero(15),
+            Equal(17, 1),
+            Zero(19),
+            Zero(20),
+            Zero(21),
+            Zero(22),
+        ],
+    ),
+    (
+        8,
+        &[
+            One(13),
+            One(14),
+            One(15),
+            Zero(17),
+            Zero(19),
+            Zero(20),
+            Zero(21),
+            One(22),
+            Equal(23, 1),
+            Equal(26, 1),
+        ],
+    ),
+    (
+        9,
+        &[
+            One(13),
+            One(14),
+            One(15),
+            Zero(17),
+            Zero(20),
+            One(21),
+            One(22),
+            Zero(23),
+            One(26),
+            Equal(30, 1),
+        ],
+    ),
+    (
+        10,
+        &[
+            One(17),
+            Zero(20),
+            Zero(21),
+            Zero(22),
+            Zero(23),
+            Zero(26),
+            One(30),
+            Equal(32, 1),
+        ],
+    ),
+    (
+        11,
+        &[
+            Zero(20),
+            One(21),
+            One(22),
+            Equal(23, 1),
+            One(26),
+            Zero(30),
+            Zero(32),
+        ],
+    ),
+    (
+        12,
+        &[
+            Zero(23),
+            Zero(26),
+            Equal(27, 1),
+            Equal(29, 1),
+            One(30),
+            Zero(32),
+        ],
+    ),
+    (
+        13,
+        &[Zero(23), Zero(26), One(27), One(29), Zero(30), One(32)],
+    ),
+    (
+        14,
+        &[Equal(19, 1), One(23), One(26), Zero(27), Zero(29), Zero(30)],
+    ),
+    (15, &[Zero(19), Equal(26, 1), One(27), One(29), Zero(30)]),
+    (16, &[Equal(19, 2), One(26), Zero(27), One(29), One(32)]),
+    (
+        17,
+        &[
+            Equal(19, 1),
+            Equal(26, 2),
+            Equal(27, 2),
+            Equal(29, 2),
+            Equal(32, 2),
+        ],
+    ),
+    (
+        18,
+        &[
+            Equal(26, 1),
+            Equal(27, 1),
+            Equal(29, 1),
+            Equal(30, 1),
+            Equal(32, 1),
+        ],
+    ),
+    (19, &[Equal(29, 1), One(30), Zero(32)]),
+    (20, &[One(29), One(32)]),
+    (21, &[Equal(29, 2)]),
+    (22, &[Equal(29, 1), NotEqual(30, 1), NotEqual(32, 1)]),
+    (35, &[One(32)]),
+    (36, &[One(32)]),
+];
+
+pub fn step_name(step: usize) -> String {
+    let register = ["a", "d", "c", "b"][step % 4];
+    format!("{}{}", register, step / 4 + 1)
+}
+
+fn bit(value: u32, bit: u32) -> u32 {
+    (value >> (bit - 1)) & 1
+}
+
+impl Condition {
+    pub fn bit(&self) -> u32 {
+        match self {
+            Zero(bit) | One(bit) | Equal(bit, _) | NotEqual(bit, _) => *bit,
+        }
+    }
+
+    // the value the bit should take, given the trace up to this step
+    fn wanted(&self, q: &[u32; 52], i: usize) -> u32 {
+        match self {
+            Zero(_) => 0,
+            One(_) => 1,
+            Equal(b, back) => bit(q[i - back], *b),
+            NotEqual(b, back) => bit(q[i - back], *b) ^ 1,
+        }
+    }
+
+    fn holds(&self, q: &[u32; 52], i: usize) -> bool {
+        bit(q[i], self.bit()) == self.wanted(q, i)
+    }
+
+    fn apply(&self, q: &[u32; 52], i: usize) -> u32 {
+        let mask = 1 << (self.bit() - 1);
+        (q[i] & !mask) | (self.wanted(q, i) << (self.bit() - 1))
+    }
+}
+
+// lists every condition the block fails when hashed from the MD4 initial
+// state, as (step, condition). an empty list doesn't guarantee a collision,
+// since the conditions are only sufficient with high probability
+pub fn unsatisfied_conditions(block: &[u8]) -> Result<Vec<(usize, Condition)>> {
+    let q = md4::trace(&md4::INITIAL_STATE, &md4::words_from_block(block)?);
+
+    Ok(CONDITIONS
+        .iter()
+        .flat_map(|(step, conditions)| conditions.iter().map(move |c| (*step, *c)))
+        .filter(|(step, condition)| !condition.holds(&q, step + 4))
+        .collect())
+}
+
+fn conditions_hold(q: &[u32; 52]) -> bool {
+    CONDITIONS
+        .iter()
+        .all(|(step, conditions)| conditions.iter().all(|c| c.holds(q, step + 4)))
+}
+
+// the message word that makes a first round step output q[i], given the
+// outputs before it
+fn round_1_word(q: &[u32; 52], step: usize) -> u32 {
+    let i = step + 4;
+    q[i].rotate_right(md4::shift_for_step(step))
+        .wrapping_sub(q[i - 4])
+        .wrapping_sub(md4::step_addend(step, q[i - 1], q[i - 2], q[i - 3]))
+}
+
+// whether a bit of a first round output can change without breaking a
+// condition on it, or on a later step that compares against it
+fn is_free_bit(step: usize, b: u32) -> bool {
+    CONDITIONS.iter().all(|(other_step, conditions)| {
+        conditions.iter().all(|c| {
+            c.bit() != b
+                || match c {
+                    Zero(_) | One(_) => *other_step != step,
+                    Equal(_, back) | NotEqual(_, back) => {
+                        *other_step != step && *other_step != step + back
+                    }
+                }
+        })
+    })
+}
+
+// single-step modification: walks the first round, forcing each output to
+// meet its conditions and solving for the message word that produces it
+fn modify_round_1(words: &mut [u32; 16]) -> [u32; 52] {
+    let mut q = md4::trace(&md4::INITIAL_STATE, words);
+
+    for (step, conditions) in CONDITIONS.iter().take_while(|(step, _)| *step < 16) {
+        let i = step + 4;
+        q[i] = q[i - 4]
+            .wrapping_add(md4::step_addend(*step, q[i - 1], q[i - 2], q[i - 3]))
+            .wrapping_add(words[*step])
+            .rotate_left(md4::shift_for_step(*step));
+        for condition in conditions.iter() {
+            q[i] = condition.apply(&q, i);
+        }
+        words[*step] = round_1_word(&q, *step);
+    }
+
+    md4::trace(&md4::INITIAL_STATE, words)
+}
+
+// multi-step modification for a second round step. it adds in the same
+// message word as one of the first round steps, so flipping the right bit of
+// that step's output flips the wanted bit here. the first round words that
+// output feeds into are re-solved so the rest of the first round is unchanged
+fn modify_round_2_step(words: &mut [u32; 16], q: &mut [u32; 52], step: usize) {
+    let shift = md4::shift_for_step(step);
+    let source_step = md4::WORD_ORDER[step];
+    let source_shift = md4::shift_for_step(source_step);
+
+    let (_, conditions) = CONDITIONS[step];
+    for condition in conditions.iter() {
+        if condition.holds(q, step + 4) {
+            continue;
+        }
+
+        // bit b of this step comes from bit b - shift of the word, which
+        // comes from bit b - shift + source_shift of the source output
+        let source_bit = (condition.bit() + 64 - shift + source_shift - 1) % 32 + 1;
+        if !is_free_bit(source_step, source_bit) {
+            continue;
+        }
+
+        q[source_step + 4] ^= 1 << (source_bit - 1);
+        let affected = source_step..(source_step + 5).min(16);
+        for (word, affected_step) in words[affected.clone()].iter_mut().zip(affected) {
+            *word = round_1_word(q, affected_step);
+        }
+        *q = md4::trace(&md4::INITIAL_STATE, words);
+    }
+}
+
+pub struct Md4Collision {
+    pub message_a: Vec<u8>,
+    pub message_b: Vec<u8>,
+    pub attempts: usize,
+}
+
+// the differential: M' differs from M in words 1, 2 and 12
+pub fn apply_differential(words: &[u32; 16]) -> [u32; 16] {
+    let mut other = *words;
+    other[1] = other[1].wrapping_add(1 << 31);
+    other[2] = other[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+    other[12] = other[12].wrapping_sub(1 << 16);
+    other
+}
+
+// searches for a pair of single block messages with the same MD4 digest.
+// each attempt fixes up a random block with message modification, first the
+// whole first round, then the a5, d5 and c5 conditions where that's
+// possible, and only compresses both blocks once the remaining cheap checks
+// pass. gives up after max_attempts blocks
+pub fn find_md4_collision(max_attempts: usize) -> Result<Md4Collision> {
+    let mut rng = rand::thread_rng();
+
+    for attempts in 1..=max_attempts {
+        let mut words = [0u32; 16];
+        rng.fill(&mut words[..]);
+
+        let mut q = modify_round_1(&mut words);
+        modify_round_2_step(&mut words, &mut q, 16);
+        modify_round_2_step(&mut words, &mut q, 17);
+        modify_round_2_step(&mut words, &mut q, 18);
+
+        if !conditions_hold(&q) {
+            continue;
+        }
+
+        let other = apply_differential(&words);
+        let mut state_a = md4::INITIAL_STATE;
+        let mut state_b = md4::INITIAL_STATE;
+        md4::compress_words(&mut state_a, &words);
+        md4::compress_words(&mut state_b, &other);
+
+        if state_a == state_b {
+            return Ok(Md4Collision {
+                message_a: md4::block_from_words(&words),
+                message_b: md4::block_from_words(&other),
+                attempts,
+            });
+        }
+    }
+
+    Err(Error::Exhausted(max_attempts))
+}
+
+#[test]
+fn test_md4_collision() {
+    let collision = find_md4_collision(1 << 24).unwrap();
+
+    assert_ne!(collision.message_a, collision.message_b);
+    assert_eq!(
+        md4::digest(&collision.message_a),
+        md4::digest(&collision.message_b)
+    );
+    assert!(collision.attempts >= 1);
+    assert!(unsatisfied_conditions(&collision.message_a[..60]).is_err());
+
+    assert!(matches!(find_md4_collision(0), Err(Error::Exhausted(0))));
+}
@@ -0,0 +1,672 @@
+use crate::byte_buffer::{ByteBuffer, ByteBufferDisplayFormat};
+use crate::cypher;
+use crate::error::{Error, Result};
+use openssl::symm::Mode;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+pub const BLOCK_SIZE: usize = 16;
+
+// the birthday searches keep 2^(b/2) states around for a b bit state, and
+// 2^24 of them is about as many as fit in memory
+const MAX_TABLE_BITS: usize = 24;
+// how many times the expected number of tries a search makes before giving up
+const TRIES_FACTOR: usize = 16;
+
+// a deliberately weak Merkle-Damgard hash. the compression function encrypts
+// the message block under the (zero padded) chaining state as an AES key and
+// keeps only the first state_bits of the output, so collisions are cheap
+pub struct WeakHash {
+    state_len: usize,
+    initial_state: Vec<u8>,
+    calls: Cell<usize>,
+}
+
+impl WeakHash {
+    pub fn new(state_bits: usize) -> Result<WeakHash> {
+        if state_bits == 0 || state_bits > 128 || !state_bits.is_multiple_of(8) {
+            return Err(Error::InvalidInput(
+                "state size must be a whole number of bytes, at most one block",
+            ));
+        }
+
+        let state_len = state_bits / 8;
+        Ok(WeakHash {
+            state_len,
+            initial_state: (0..state_len).map(|i| 0x5a ^ (i as u8)).collect(),
+            calls: Cell::new(0),
+        })
+    }
+
+    pub fn state_len(&self) -> usize {
+        self.state_len
+    }
+
+    pub fn initial_state(&self) -> Vec<u8> {
+        self.initial_state.clone()
+    }
+
+    // the number of times the compression function has run on this hash
+    pub fn calls(&self) -> usize {
+        self.calls.get()
+    }
+
+    pub fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        self.calls.set(self.calls.get() + 1);
+
+        let mut key = state.to_vec();
+        key.resize(BLOCK_SIZE, 0);
+
+        let mut output = cypher::aes_block(block, &key, Mode::Encrypt)
+            .expect("the key was just resized to a block");
+        output.truncate(self.state_len);
+        output
+    }
+
+    // runs the compression function over whole blocks, without any padding
+    pub fn compress_blocks(&self, state: &[u8], blocks: &[u8]) -> Vec<u8> {
+        blocks
+            .chunks(BLOCK_SIZE)
+            .fold(state.to_vec(), |state, block| self.compress(&state, block))
+    }
+
+    pub fn hash(&self, msg: &[u8]) -> Vec<u8> {
+        self.compress_blocks(&self.initial_state, &md_pad(msg, msg.len()))
+    }
+}
+
+// appends a 1 bit, zeros, and the message length in bits as a big endian u64,
+// so the result is a whole number of blocks. total_len is usually msg.len(),
+// but can differ when msg is the tail of something longer
+pub fn md_pad(msg: &[u8], total_len: usize) -> Vec<u8> {
+    let mut padded = msg.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((total_len as u64) * 8).to_be_bytes());
+
+    padded
+}
+
+#[derive(Clone, Debug)]
+pub struct Collision {
+    pub state: Vec<u8>,
+    pub block_a: Vec<u8>,
+    pub block_b: Vec<u8>,
+    pub next_state: Vec<u8>,
+}
+
+// 2^(b/2), the number of states a birthday search expects to go through
+fn table_size(hash: &WeakHash) -> Result<usize> {
+    let bits = hash.state_len() * 4;
+    if bits > MAX_TABLE_BITS {
+        return Err(Error::InvalidInput("state too wide to tabulate"));
+    }
+    Ok(1 << bits)
+}
+
+// finds two distinct blocks taking state to the same next state by hashing
+// random blocks until two land together
+pub fn find_collision(hash: &WeakHash, state: &[u8]) -> Result<Collision> {
+    let tries = table_size(hash)? * TRIES_FACTOR;
+    let mut seen: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+
+    for _ in 0..tries {
+        let block = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+        let next_state = hash.compress(state, &block);
+
+        match seen.get(&next_state) {
+            Some(seen_block) if *seen_block != block => {
+                return Ok(Collision {
+                    state: state.to_vec(),
+                    block_a: seen_block.clone(),
+                    block_b: block,
+                    next_state,
+                })
+            }
+            _ => {
+                seen.insert(next_state, block);
+            }
+        }
+    }
+
+    Err(Error::Exhausted(tries))
+}
+
+pub struct MultiCollision {
+    pub collisions: Vec<Collision>,
+    pub compression_calls: usize,
+}
+
+impl MultiCollision {
+    // None when there are no collisions, so nothing to chain from
+    pub fn final_state(&self) -> Option<Vec<u8>> {
+        self.collisions
+            .last()
+            .map(|collision| collision.next_state.clone())
+    }
+
+    // the number of colliding messages, two for every chained collision. None
+    // once that's too many to count in a usize
+    pub fn message_count(&self) -> Option<usize> {
+        u32::try_from(self.collisions.len())
+            .ok()
+            .and_then(|len| 1usize.checked_shl(len))
+    }
+
+    // bit i of index picks which block of the i-th collision to use
+    pub fn message(&self, index: usize) -> Vec<u8> {
+        self.collisions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, collision)| {
+                if (index >> i) & 1 == 0 {
+                    collision.block_a.clone()
+                } else {
+                    collision.block_b.clone()
+                }
+            })
+            .collect()
+    }
+
+    pub fn dump(&self) -> String {
+        let count = match self.message_count() {
+            Some(count) => count.to_string(),
+            None => format!("2^{}", self.collisions.len()),
+        };
+        let mut s = format!(
+            "{} colliding messages from {} compression calls\n",
+            count, self.compression_calls
+        );
+
+        for (i, collision) in self.collisions.iter().enumerate() {
+            s.push_str(&format!(
+                "block {}: {} -> {}\n",
+                i,
+                collision.state.to_string(ByteBufferDisplayFormat::Hex),
+                collision.next_state.to_string(ByteBufferDisplayFormat::Hex)
+            ));
+            s.push_str(&collision.block_a.to_string(ByteBufferDisplayFormat::Grid));
+            s.push_str(&collision.block_b.to_string(ByteBufferDisplayFormat::Grid));
+        }
+
+        s
+    }
+}
+
+// Joux's trick: chaining n single-block collisions gives 2^n messages of n
+// blocks that all collide, for only n times the work of one collision
+pub fn find_multicollision(hash: &WeakHash, state: &[u8], n: usize) -> Result<MultiCollision> {
+    let calls_before = hash.calls();
+
+    let mut collisions: Vec<Collision> = Vec::with_capacity(n);
+    let mut state = state.to_vec();
+    for _ in 0..n {
+        let collision = find_collision(hash, &state)?;
+        state = collision.next_state.clone();
+        collisions.push(collision);
+    }
+
+    Ok(MultiCollision {
+        collisions,
+        compression_calls: hash.calls() - calls_before,
+    })
+}
+
+pub struct CascadeCollision {
+    pub message_a: Vec<u8>,
+    pub message_b: Vec<u8>,
+    pub multicollision: MultiCollision,
+    pub cheap_calls: usize,
+    pub expensive_calls: usize,
+}
+
+impl CascadeCollision {
+    pub fn dump(&self) -> String {
+        format!(
+            "{} cheap and {} expensive compression calls\n{}\n{}\n{}",
+            self.cheap_calls,
+            self.expensive_calls,
+            self.multicollision.dump(),
+            self.message_a.to_string(ByteBufferDisplayFormat::Grid),
+            self.message_b.to_string(ByteBufferDisplayFormat::Grid)
+        )
+    }
+}
+
+// walks every message in the multicollision through the expensive hash,
+// sharing the work for common prefixes, looking for two that collide
+fn find_expensive_collision(
+    expensive: &WeakHash,
+    multicollision: &MultiCollision,
+    depth: usize,
+    state: Vec<u8>,
+    index: usize,
+    seen: &mut HashMap<Vec<u8>, usize>,
+) -> Option<(usize, usize)> {
+    if depth == multicollision.collisions.len() {
+        if let Some(seen_index) = seen.get(&state) {
+            return Some((*seen_index, index));
+        }
+        seen.insert(state, index);
+        return None;
+    }
+
+    let collision = &multicollision.collisions[depth];
+    for (bit, block) in [&collision.block_a, &collision.block_b].iter().enumerate() {
+        let next_state = expensive.compress(&state, block);
+        let found = find_expensive_collision(
+            expensive,
+            multicollision,
+            depth + 1,
+            next_state,
+            index | (bit << depth),
+            seen,
+        );
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    None
+}
+
+// finds a collision in cheap(m) || expensive(m) for about the cost of a
+// collision in the expensive hash alone: a 2^(b/2) multicollision in the cheap
+// hash is likely to contain a pair colliding in the expensive one too, where b
+// is the expensive hash's state size. if it doesn't, it's grown by a block,
+// up to TRIES_FACTOR times the messages it started with
+pub fn find_cascade_collision(cheap: &WeakHash, expensive: &WeakHash) -> Result<CascadeCollision> {
+    table_size(expensive)?;
+    let cheap_calls_before = cheap.calls();
+    let expensive_calls_before = expensive.calls();
+
+    let mut multicollision =
+        find_multicollision(cheap, &cheap.initial_state(), expensive.state_len() * 8 / 2)?;
+
+    let extensions = TRIES_FACTOR.ilog2() as usize;
+    for _ in 0..=extensions {
+        let mut seen = HashMap::new();
+        let found = find_expensive_collision(
+            expensive,
+            &multicollision,
+            0,
+            expensive.initial_state(),
+            0,
+            &mut seen,
+        );
+
+        if let Some((index_a, index_b)) = found {
+            return Ok(CascadeCollision {
+                message_a: multicollision.message(index_a),
+                message_b: multicollision.message(index_b),
+                cheap_calls: cheap.calls() - cheap_calls_before,
+                expensive_calls: expensive.calls() - expensive_calls_before,
+                multicollision,
+            });
+        }
+
+        let state = multicollision
+            .final_state()
+            .unwrap_or_else(|| cheap.initial_state());
+        let extension = find_collision(cheap, &state)?;
+        multicollision.collisions.push(extension);
+        multicollision.compression_calls = cheap.calls() - cheap_calls_before;
+    }
+
+    Err(Error::NoCandidate(
+        "no pair of messages collides in the expensive hash",
+    ))
+}
+
+// finds a block for each of two different states that take them to the same
+// next state, by tabulating 2^(b/2) outputs from the first and trying blocks
+// on the second until one lands in the table, which has to fit in memory
+pub fn find_cross_collision(hash: &WeakHash, state_a: &[u8], state_b: &[u8]) -> Result<Collision> {
+    let table_size = table_size(hash)?;
+    let tries = table_size * TRIES_FACTOR;
+    let mut table: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    for _ in 0..tries {
+        if table.len() == table_size {
+            break;
+        }
+        let block = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+        table.insert(hash.compress(state_a, &block), block);
+    }
+
+    for _ in 0..tries {
+        let block = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+        let next_state = hash.compress(state_b, &block);
+        if let Some(block_a) = table.get(&next_state) {
+            return Ok(Collision {
+                state: state_a.to_vec(),
+                block_a: block_a.clone(),
+                block_b: block,
+                next_state,
+            });
+        }
+    }
+
+    Err(Error::Exhausted(tries))
+}
+
+pub struct ExpandablePiece {
+    pub short: Vec<u8>,
+    pub long: Vec<u8>,
+}
+
+// a (k, k + 2^k - 1) expandable message: piece i collides a single block with
+// 2^(k - 1 - i) dummy blocks plus one, so picking short or long for each piece
+// gives a message of any block length in that range, all ending in final_state
+pub struct ExpandableMessage {
+    pub pieces: Vec<ExpandablePiece>,
+    pub final_state: Vec<u8>,
+    pub compression_calls: usize,
+}
+
+impl ExpandableMessage {
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1 << self.pieces.len()) - 1
+    }
+
+    pub fn message(&self, blocks: usize) -> Result<Vec<u8>> {
+        if blocks < self.min_blocks() || blocks > self.max_blocks() {
+            return Err(Error::OutOfRange(blocks));
+        }
+
+        let extra_blocks = blocks - self.min_blocks();
+        let k = self.pieces.len();
+        let message = self
+            .pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(i, piece)| {
+                if (extra_blocks >> (k - 1 - i)) & 1 == 0 {
+                    piece.short.clone()
+                } else {
+                    piece.long.clone()
+                }
+            })
+            .collect();
+
+        Ok(message)
+    }
+}
+
+pub fn make_expandable_message(
+    hash: &WeakHash,
+    state: &[u8],
+    k: usize,
+) -> Result<ExpandableMessage> {
+    let calls_before = hash.calls();
+
+    let mut pieces = Vec::with_capacity(k);
+    let mut state = state.to_vec();
+    for i in (0..k).rev() {
+        let dummy = vec![0u8; BLOCK_SIZE << i];
+        let dummy_state = hash.compress_blocks(&state, &dummy);
+
+        let collision = find_cross_collision(hash, &state, &dummy_state)?;
+        pieces.push(ExpandablePiece {
+            short: collision.block_a,
+            long: [dummy, collision.block_b].concat(),
+        });
+        state = collision.next_state;
+    }
+
+    Ok(ExpandableMessage {
+        pieces,
+        final_state: state,
+        compression_calls: hash.calls() - calls_before,
+    })
+}
+
+pub struct SecondPreimage {
+    pub message: Vec<u8>,
+    pub compression_calls: usize,
+}
+
+// Kelsey-Schneier: for a target of about 2^k blocks, build a (k, k + 2^k - 1)
+// expandable message, then search for a bridge block from its final state to
+// any of the target's intermediate states. the expandable message is sized so
+// the forgery is as long as the target, which makes the padding agree too
+pub fn find_second_preimage(hash: &WeakHash, target: &[u8]) -> Result<SecondPreimage> {
+    let calls_before = hash.calls();
+
+    let target_blocks = target.len() / BLOCK_SIZE;
+    if target_blocks < 2 {
+        return Err(Error::InvalidInput("target must be at least two blocks"));
+    }
+    let k = (usize::BITS - 1 - target_blocks.leading_zeros()) as usize;
+
+    let expandable = make_expandable_message(hash, &hash.initial_state(), k)?;
+
+    // intermediate states we can bridge to, by how many blocks reach them
+    let mut intermediate: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut state = hash.initial_state();
+    for (i, block) in target.chunks(BLOCK_SIZE).take(target_blocks).enumerate() {
+        state = hash.compress(&state, block);
+        let blocks = i + 1;
+        if blocks > expandable.min_blocks() && blocks <= expandable.max_blocks() + 1 {
+            intermediate.entry(state.clone()).or_insert(blocks);
+        }
+    }
+
+    loop {
+        let bridge = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+        let next_state = hash.compress(&expandable.final_state, &bridge);
+        if let Some(blocks) = intermediate.get(&next_state) {
+            let prefix = expandable.message(blocks - 1)?;
+            let message = [&prefix[..], &bridge[..], &target[(blocks * BLOCK_SIZE)..]].concat();
+
+            return Ok(SecondPreimage {
+                message,
+                compression_calls: hash.calls() - calls_before,
+            });
+        }
+    }
+}
+
+// a diamond structure for herding. levels[0] holds 2^depth random leaf states,
+// and each entry holds the block taking it to entry i / 2 of the next level,
+// where it collides with its sibling, until everything meets at the root
+pub struct Diamond {
+    pub levels: Vec<Vec<(Vec<u8>, Vec<u8>)>>,
+    pub root: Vec<u8>,
+    pub prefix_blocks: usize,
+    pub compression_calls: usize,
+}
+
+impl Diamond {
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    // the length of any herded message: the prefix, a linking block, and a
+    // path through the diamond
+    pub fn message_len(&self) -> usize {
+        (self.prefix_blocks + 1 + self.depth()) * BLOCK_SIZE
+    }
+
+    // the hash to publish ahead of time. every herded message is the same
+    // length, so the final padding block is known in advance
+    pub fn commitment(&self, hash: &WeakHash) -> Vec<u8> {
+        let padding = md_pad(&[], self.message_len());
+        hash.compress_blocks(&self.root, &padding)
+    }
+
+    // produces a message that starts with prefix (space padded out to the
+    // diamond's prefix length) and hashes to the commitment, by searching for
+    // a block linking the prefix to any leaf
+    pub fn herd(&self, hash: &WeakHash, prefix: &[u8]) -> Result<Vec<u8>> {
+        if prefix.len() > self.prefix_blocks * BLOCK_SIZE {
+            return Err(Error::InvalidInput(
+                "prefix longer than the diamond's prefix blocks",
+            ));
+        }
+        let mut message = prefix.to_vec();
+        message.resize(self.prefix_blocks * BLOCK_SIZE, b' ');
+
+        // a diamond of depth 0 is only its root
+        let leaves: HashMap<Vec<u8>, usize> = match self.levels.first() {
+            Some(level) => level
+                .iter()
+                .enumerate()
+                .map(|(i, (state, _))| (state.clone(), i))
+                .collect(),
+            None => HashMap::from([(self.root.clone(), 0)]),
+        };
+        let prefix_state = hash.compress_blocks(&hash.initial_state(), &message);
+
+        let mut index = loop {
+            let link = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+            if let Some(index) = leaves.get(&hash.compress(&prefix_state, &link)) {
+                message.extend(link);
+                break *index;
+            }
+        };
+
+        for level in self.levels.iter() {
+            message.extend(&level[index].1);
+            index /= 2;
+        }
+
+        Ok(message)
+    }
+}
+
+// builds a diamond of the given depth, which costs about 2^(depth + b/2 + 1)
+// compression calls, and brings herding down to about 2^(b - depth)
+pub fn build_diamond(hash: &WeakHash, depth: usize, prefix_blocks: usize) -> Result<Diamond> {
+    let calls_before = hash.calls();
+
+    let leaves = u32::try_from(depth)
+        .ok()
+        .and_then(|depth| 1usize.checked_shl(depth))
+        .ok_or(Error::InvalidInput("diamond too deep"))?;
+    let mut states: Vec<Vec<u8>> = (0..leaves)
+        .map(|_| Vec::<u8>::from_rand_bytes(hash.state_len()))
+        .collect();
+    let mut levels = Vec::with_capacity(depth);
+
+    while states.len() > 1 {
+        let mut level = Vec::with_capacity(states.len());
+        let mut next_states = Vec::with_capacity(states.len() / 2);
+
+        for pair in states.chunks(2) {
+            let collision = find_cross_collision(hash, &pair[0], &pair[1])?;
+            level.push((pair[0].clone(), collision.block_a));
+            level.push((pair[1].clone(), collision.block_b));
+            next_states.push(collision.next_state);
+        }
+
+        levels.push(level);
+        states = next_states;
+    }
+
+    Ok(Diamond {
+        levels,
+        root: states.remove(0),
+        prefix_blocks,
+        compression_calls: hash.calls() - calls_before,
+    })
+}
+
+#[test]
+fn test_multicollision() {
+    let hash = WeakHash::new(16).unwrap();
+    let multicollision = find_multicollision(&hash, &hash.initial_state(), 4).unwrap();
+    assert_eq!(multicollision.message_count(), Some(16));
+
+    let digest = hash.hash(&multicollision.message(0));
+    for index in 1..16 {
+        assert_ne!(multicollision.message(index), multicollision.message(0));
+        assert_eq!(hash.hash(&multicollision.message(index)), digest);
+    }
+
+    let empty = find_multicollision(&hash, &hash.initial_state(), 0).unwrap();
+    assert_eq!(empty.final_state(), None);
+    assert_eq!(empty.message_count(), Some(1));
+
+    let collision = multicollision.collisions[0].clone();
+    let huge = MultiCollision {
+        collisions: vec![collision; 64],
+        compression_calls: 0,
+    };
+    assert_eq!(huge.message_count(), None);
+    assert!(huge.dump().starts_with("2^64 colliding messages"));
+
+    // a birthday search at 64 bits would need a table of 2^32 states
+    let wide = WeakHash::new(64).unwrap();
+    assert!(matches!(
+        find_collision(&wide, &wide.initial_state()),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_cascade_collision() {
+    let cheap = WeakHash::new(16).unwrap();
+    let expensive = WeakHash::new(24).unwrap();
+    let collision = find_cascade_collision(&cheap, &expensive).unwrap();
+
+    assert_ne!(collision.message_a, collision.message_b);
+    assert_eq!(
+        cheap.hash(&collision.message_a),
+        cheap.hash(&collision.message_b)
+    );
+    assert_eq!(
+        expensive.hash(&collision.message_a),
+        expensive.hash(&collision.message_b)
+    );
+
+    let wide = WeakHash::new(96).unwrap();
+    assert!(find_cascade_collision(&cheap, &wide).is_err());
+}
+
+#[test]
+fn test_second_preimage() {
+    let hash = WeakHash::new(16).unwrap();
+    let target = Vec::<u8>::from_rand_bytes(BLOCK_SIZE * 256 + 5);
+    let forged = find_second_preimage(&hash, &target).unwrap();
+
+    assert_ne!(forged.message, target);
+    assert_eq!(forged.message.len(), target.len());
+    assert_eq!(hash.hash(&forged.message), hash.hash(&target));
+}
+
+#[test]
+fn test_herding() {
+    let hash = WeakHash::new(16).unwrap();
+    let diamond = build_diamond(&hash, 6, 2).unwrap();
+    let commitment = diamond.commitment(&hash);
+
+    let message = diamond.herd(&hash, b"final score: 3-1").unwrap();
+    assert!(message.starts_with(b"final score: 3-1"));
+    assert_eq!(message.len(), diamond.message_len());
+    assert_eq!(hash.hash(&message), commitment);
+
+    let flat = build_diamond(&hash, 0, 1).unwrap();
+    let message = flat.herd(&hash, b"no diamond").unwrap();
+    assert_eq!(hash.hash(&message), flat.commitment(&hash));
+
+    let wide = WeakHash::new(128).unwrap();
+    let state = wide.initial_state();
+    assert!(find_cross_collision(&wide, &state, &state).is_err());
+    // 2^48 states would be wanted in the table at 96 bits
+    let wide = WeakHash::new(96).unwrap();
+    let state = wide.initial_state();
+    assert!(matches!(
+        find_cross_collision(&wide, &state, &state),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(build_diamond(&hash, 64, 1).is_err());
+    assert!(flat.herd(&hash, &[b' '; 17]).is_err());
+    assert!(matches!(WeakHash::new(12), Err(Error::InvalidInput(_))));
+    assert!(WeakHash::new(136).is_err());
+}
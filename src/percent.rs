@@ -1,54 +1,94 @@
 enum PercentDecodeConsumeState {
     Passthrough,
-    EncodeByte(Option<u8>),
+    // the first digit as it was written, and its value
+    EncodeByte(Option<(u8, u8)>),
 }
 
 fn byte_is_hex_char(byte: u8) -> Option<u8> {
     match byte {
         0x30..=0x39 => Some(byte - 0x30),
-        0x41..0x46 => Some(10 + byte - 0x41),
-        0x61..0x66 => Some(10 + byte - 0x61),
-        _ => None
+        0x41..=0x46 => Some(10 + byte - 0x41),
+        0x61..=0x66 => Some(10 + byte - 0x61),
+        _ => None,
     }
 }
 
-pub fn decode(buffer: &Vec<u8>) -> Vec<u8> {
+const HEX_CHARS: &[u8; 16] = b"0123456789ABCDEF";
+
+// escapes '%', every byte in reserved, and anything that isn't printable ascii
+pub fn encode(buffer: &[u8], reserved: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(buffer.len());
+
+    for byte in buffer {
+        if *byte == 0x25 || reserved.contains(byte) || !byte.is_ascii_graphic() {
+            encoded.push(0x25);
+            encoded.push(HEX_CHARS[(byte >> 4) as usize]);
+            encoded.push(HEX_CHARS[(byte & 0x0f) as usize]);
+        } else {
+            encoded.push(*byte);
+        }
+    }
+
+    encoded
+}
+
+// a '%' that isn't followed by two hex digits is passed through as it is
+pub fn decode(buffer: &[u8]) -> Vec<u8> {
     use PercentDecodeConsumeState::*;
 
-    let decoded = Vec::new();
+    let mut decoded = Vec::with_capacity(buffer.len());
 
     let mut state = Passthrough;
 
     for byte in buffer {
+        let byte = *byte;
         match state {
             Passthrough => {
                 if byte == 0x25 {
-                    state = EncodeByte(None, None);
+                    state = EncodeByte(None);
                 } else {
-                    state.push(byte);
+                    decoded.push(byte);
                 }
-            },
+            }
             EncodeByte(None) => {
                 if let Some(upper) = byte_is_hex_char(byte) {
-                    state = EncodeByte(Some(upper));
+                    state = EncodeByte(Some((byte, upper)));
                 } else {
                     decoded.push(0x25);
                     decoded.push(byte);
                     state = Passthrough;
                 }
-            },
-            EncodeByte(Some(upper)) => {
+            }
+            EncodeByte(Some((first, upper))) => {
                 if let Some(lower) = byte_is_hex_char(byte) {
-                    let decoded_byte = (upper << 4) | lower;
-                    decoded.push(decoded_byte);
-                    state = Passthrough;
+                    decoded.push((upper << 4) | lower);
                 } else {
                     decoded.push(0x25);
                     decoded.push(first);
                     decoded.push(byte);
-                    state = Passthrough;
                 }
-            },
+                state = Passthrough;
+            }
+        }
+    }
+
+    match state {
+        Passthrough => {}
+        EncodeByte(None) => decoded.push(0x25),
+        EncodeByte(Some((first, _))) => {
+            decoded.push(0x25);
+            decoded.push(first);
         }
     }
+
+    decoded
+}
+
+#[test]
+fn test_percent() {
+    assert_eq!(encode(b"a=b&c d%", b"=&"), b"a%3Db%26c%20d%25".to_vec());
+    assert_eq!(decode(b"a%3Db%26c%20d%25"), b"a=b&c d%".to_vec());
+    assert_eq!(decode(b"%3d%fF"), vec![b'=', 0xff]);
+    assert_eq!(decode(b"100%"), b"100%".to_vec());
+    assert_eq!(decode(b"%zz%a"), b"%zz%a".to_vec());
 }
@@ -0,0 +1,448 @@
+use crate::byte_buffer::ByteBuffer;
+use crate::cypher::{self, BLOCK_SIZE};
+use crate::error::{Error, Result};
+use crate::kv::{Duplicates, KvCodec, KvMap, Separators};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher, Mode};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const KEY_LEN: usize = 16;
+const GCM_NONCE_LEN: usize = 12;
+const GCM_TAG_LEN: usize = 16;
+
+// escaped, so a field can't break out into one of its own, and a repeated
+// field is refused rather than letting one copy quietly win
+fn codec() -> KvCodec {
+    KvCodec::new(Separators::QUERY)
+        .escaping(true)
+        .duplicates(Duplicates::Error)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::User => write!(f, "user"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Role> {
+        match s {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            _ => Err(Error::Parse(format!("Invalid role {}", s))),
+        }
+    }
+}
+
+// the seconds since the epoch, which is what expiry is measured in
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// unlike utils::Profile, every field has been checked, and a token that
+// leaves one out doesn't get a default for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub email: String,
+    pub role: Role,
+    pub uid: u32,
+    pub expires: u64,
+}
+
+pub struct ProfileBuilder {
+    email: Option<String>,
+    role: Role,
+    uid: Option<u32>,
+    expires: Option<u64>,
+}
+
+fn check_email(email: &str) -> Result<()> {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.contains('@')
+                && email.bytes().all(|byte| byte.is_ascii_graphic())
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Parse(format!("Invalid email {}", email)))
+    }
+}
+
+impl ProfileBuilder {
+    pub fn new() -> ProfileBuilder {
+        ProfileBuilder {
+            email: None,
+            role: Role::User,
+            uid: None,
+            expires: None,
+        }
+    }
+
+    pub fn email(mut self, email: &str) -> ProfileBuilder {
+        self.email = Some(email.to_owned());
+        self
+    }
+
+    pub fn role(mut self, role: Role) -> ProfileBuilder {
+        self.role = role;
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> ProfileBuilder {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn expires_at(mut self, expires: u64) -> ProfileBuilder {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn valid_for(self, duration: Duration) -> Result<ProfileBuilder> {
+        let expires = now()
+            .checked_add(duration.as_secs())
+            .ok_or(Error::InvalidInput("expiry past the end of time"))?;
+        Ok(self.expires_at(expires))
+    }
+
+    pub fn build(self) -> Result<Profile> {
+        let email = self
+            .email
+            .ok_or(Error::InvalidInput("missing field email"))?;
+        check_email(&email)?;
+
+        Ok(Profile {
+            email,
+            role: self.role,
+            uid: self.uid.ok_or(Error::InvalidInput("missing field uid"))?,
+            expires: self
+                .expires
+                .ok_or(Error::InvalidInput("missing field expires"))?,
+        })
+    }
+}
+
+impl Default for ProfileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profile {
+    // the values are percent-escaped, so an email can't smuggle in a role
+    pub fn encode(&self) -> String {
+        let mut map = KvMap::new();
+        map.push("email", self.email.as_str());
+        map.push("role", self.role.to_string());
+        map.push("uid", self.uid.to_string());
+        map.push("expires", self.expires.to_string());
+
+        codec()
+            .encode(&map)
+            .expect("escaping leaves nothing to reject")
+    }
+
+    // every field must turn up exactly once, and nothing else may
+    pub fn decode(encoded: &str) -> Result<Profile> {
+        let map = codec().decode(encoded)?;
+        if let Some((key, _)) = map
+            .iter()
+            .find(|(key, _)| !["email", "role", "uid", "expires"].contains(key))
+        {
+            return Err(Error::Parse(format!("Unexpected field {}", key)));
+        }
+        let field = |name: &str| {
+            map.get(name)
+                .ok_or_else(|| Error::Parse(format!("Missing field {}", name)))
+        };
+
+        ProfileBuilder::new()
+            .email(field("email")?)
+            .role(field("role")?.parse()?)
+            .uid(
+                field("uid")?
+                    .parse()
+                    .map_err(|_| Error::Parse(String::from("Invalid uid")))?,
+            )
+            .expires_at(
+                field("expires")?
+                    .parse()
+                    .map_err(|_| Error::Parse(String::from("Invalid expires")))?,
+            )
+            .build()
+    }
+}
+
+// how a token is encrypted. only GCM authenticates it: the others will
+// decrypt whatever they're handed, which is the point of keeping them around,
+// to show what that costs next to the one that doesn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenCipher {
+    // no IV, so identical profiles give identical tokens, and blocks can be
+    // cut and pasted between them
+    Ecb,
+    // a random IV up front
+    Cbc,
+    // a random little-endian nonce up front
+    Ctr,
+    // a random nonce up front and the tag at the end
+    Gcm,
+}
+
+// issues and checks session tokens under a key of its own
+pub struct SessionStore {
+    key: Vec<u8>,
+    cipher: TokenCipher,
+}
+
+fn split_token(token: &[u8], front: usize, back: usize) -> Result<(&[u8], &[u8], &[u8])> {
+    if token.len() < front + back {
+        return Err(Error::InvalidInput("token too short"));
+    }
+    let (head, rest) = token.split_at(front);
+    let (body, tail) = rest.split_at(rest.len() - back);
+    Ok((head, body, tail))
+}
+
+// a padded cyphertext is at least one whole block. anything else was never
+// issued, and is refused before its padding gets a look
+fn check_blocks(cyphertext: &[u8]) -> Result<()> {
+    let blocks = cyphertext.len().div_ceil(BLOCK_SIZE).max(1);
+    if cyphertext.len() != blocks * BLOCK_SIZE {
+        return Err(Error::LengthMismatch(cyphertext.len(), blocks * BLOCK_SIZE));
+    }
+    Ok(())
+}
+
+impl SessionStore {
+    pub fn new(cipher: TokenCipher) -> SessionStore {
+        SessionStore {
+            key: Vec::<u8>::from_rand_bytes(KEY_LEN),
+            cipher,
+        }
+    }
+
+    pub fn with_key(key: Vec<u8>, cipher: TokenCipher) -> Result<SessionStore> {
+        if key.len() != KEY_LEN {
+            return Err(Error::KeyLength(key.len()));
+        }
+        Ok(SessionStore { key, cipher })
+    }
+
+    pub fn cipher(&self) -> TokenCipher {
+        self.cipher
+    }
+
+    pub fn issue(&self, profile: &Profile) -> Result<Vec<u8>> {
+        self.encrypt(profile.encode().as_bytes())
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self.cipher {
+            TokenCipher::Ecb => cypher::aes_ecb(
+                &cypher::pkcs7_pad(plaintext, BLOCK_SIZE),
+                &self.key,
+                Mode::Encrypt,
+            ),
+            TokenCipher::Cbc => {
+                let iv = Vec::<u8>::from_rand_bytes(BLOCK_SIZE);
+                let cyphertext = cypher::aes_cbc(
+                    &cypher::pkcs7_pad(plaintext, BLOCK_SIZE),
+                    &self.key,
+                    &iv,
+                    Mode::Encrypt,
+                )?;
+                Ok([iv, cyphertext].concat())
+            }
+            TokenCipher::Ctr => {
+                let nonce: u64 = rand::random();
+                let cyphertext = cypher::aes_ctr(plaintext, &self.key, nonce)?;
+                Ok([nonce.to_le_bytes().to_vec(), cyphertext].concat())
+            }
+            TokenCipher::Gcm => {
+                let nonce = Vec::<u8>::from_rand_bytes(GCM_NONCE_LEN);
+                let mut tag = vec![0; GCM_TAG_LEN];
+                let cyphertext = encrypt_aead(
+                    Cipher::aes_128_gcm(),
+                    &self.key,
+                    Some(&nonce),
+                    &[],
+                    plaintext,
+                    &mut tag,
+                )?;
+                Ok([nonce, cyphertext, tag].concat())
+            }
+        }
+    }
+
+    pub fn decrypt(&self, token: &[u8]) -> Result<Vec<u8>> {
+        match self.cipher {
+            TokenCipher::Ecb => {
+                check_blocks(token)?;
+                cypher::pkcs7_unpad(
+                    cypher::aes_ecb(token, &self.key, Mode::Decrypt)?,
+                    BLOCK_SIZE,
+                )
+            }
+            TokenCipher::Cbc => {
+                let (iv, cyphertext, _) = split_token(token, BLOCK_SIZE, 0)?;
+                check_blocks(cyphertext)?;
+                cypher::pkcs7_unpad(
+                    cypher::aes_cbc(cyphertext, &self.key, iv, Mode::Decrypt)?,
+                    BLOCK_SIZE,
+                )
+            }
+            TokenCipher::Ctr => {
+                let (nonce, cyphertext, _) = split_token(token, 8, 0)?;
+                let nonce = u64::from_le_bytes(nonce.try_into().expect("split at 8 bytes"));
+                cypher::aes_ctr(cyphertext, &self.key, nonce)
+            }
+            TokenCipher::Gcm => {
+                let (nonce, cyphertext, tag) = split_token(token, GCM_NONCE_LEN, GCM_TAG_LEN)?;
+                decrypt_aead(
+                    Cipher::aes_128_gcm(),
+                    &self.key,
+                    Some(nonce),
+                    &[],
+                    cyphertext,
+                    tag,
+                )
+                .map_err(|_| Error::InvalidMac)
+            }
+        }
+    }
+
+    // the profile in token, if it decrypts, parses and hasn't expired by
+    // now. the error says which of those it failed
+    pub fn verify_at(&self, token: &[u8], now: u64) -> Result<Profile> {
+        let plaintext = self.decrypt(token)?;
+        let encoded = std::str::from_utf8(&plaintext)
+            .map_err(|_| Error::Parse(String::from("Invalid utf8")))?;
+        let profile = Profile::decode(encoded)?;
+
+        if profile.expires <= now {
+            return Err(Error::Expired(profile.expires));
+        }
+        Ok(profile)
+    }
+
+    pub fn verify(&self, token: &[u8]) -> Result<Profile> {
+        self.verify_at(token, now())
+    }
+}
+
+#[test]
+fn test_session_tokens() {
+    assert!(ProfileBuilder::new()
+        .uid(1)
+        .expires_at(100)
+        .build()
+        .is_err());
+    assert!(ProfileBuilder::new()
+        .email("no at sign")
+        .uid(1)
+        .expires_at(100)
+        .build()
+        .is_err());
+
+    // the email tries to end itself early and add a role of its own
+    let profile = ProfileBuilder::new()
+        .email("foo@bar.com&role=admin")
+        .uid(10)
+        .expires_at(1000)
+        .build()
+        .unwrap();
+    assert_eq!(
+        profile.encode(),
+        "email=foo@bar.com%26role%3Dadmin&role=user&uid=10&expires=1000"
+    );
+    assert_eq!(Profile::decode(&profile.encode()).unwrap(), profile);
+    assert!(Profile::decode("email=a@b.c&role=user&role=admin&uid=1&expires=1").is_err());
+    assert!(Profile::decode("email=a@b.c&role=user&expires=1").is_err());
+
+    for cipher in [
+        TokenCipher::Ecb,
+        TokenCipher::Cbc,
+        TokenCipher::Ctr,
+        TokenCipher::Gcm,
+    ] {
+        let store = SessionStore::new(cipher);
+        let token = store.issue(&profile).unwrap();
+        assert_eq!(store.verify_at(&token, 999).unwrap(), profile);
+        assert!(matches!(
+            store.verify_at(&token, 1000),
+            Err(Error::Expired(1000))
+        ));
+    }
+
+    assert!(ProfileBuilder::new()
+        .valid_for(Duration::from_secs(u64::MAX))
+        .is_err());
+    let fresh = ProfileBuilder::new()
+        .email("foo@bar.com")
+        .uid(10)
+        .valid_for(Duration::from_secs(60))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(fresh.expires > now());
+
+    let store = SessionStore::new(TokenCipher::Cbc);
+    let mut token = store.issue(&profile).unwrap();
+    // through the block before, so the padding byte itself changes
+    let position = token.len() - 17;
+    token[position] ^= 1;
+    assert!(matches!(store.verify_at(&token, 0), Err(Error::Padding)));
+
+    // a token cut short mid-block was never issued, so it isn't a padding error
+    assert!(matches!(
+        store.verify_at(&token[..token.len() - 1], 0),
+        Err(Error::LengthMismatch(_, _))
+    ));
+    assert!(matches!(
+        store.verify_at(&token[..16], 0),
+        Err(Error::LengthMismatch(0, 16))
+    ));
+    let store = SessionStore::new(TokenCipher::Ecb);
+    assert!(matches!(
+        store.verify_at(&[0; 20], 0),
+        Err(Error::LengthMismatch(20, 32))
+    ));
+    assert!(matches!(
+        store.verify_at(&[], 0),
+        Err(Error::LengthMismatch(0, 16))
+    ));
+
+    // CTR lets a flipped bit through to the plaintext, GCM doesn't
+    let store = SessionStore::new(TokenCipher::Ctr);
+    let mut token = store.issue(&profile).unwrap();
+    let position = 8 + profile.encode().find("uid=10").unwrap() + 5;
+    token[position] ^= b'0' ^ b'1';
+    assert_eq!(store.verify_at(&token, 0).unwrap().uid, 11);
+
+    let store = SessionStore::new(TokenCipher::Gcm);
+    let mut token = store.issue(&profile).unwrap();
+    token[GCM_NONCE_LEN] ^= 1;
+    assert!(matches!(store.verify_at(&token, 0), Err(Error::InvalidMac)));
+}
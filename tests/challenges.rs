@@ -0,0 +1,102 @@
+use cryptopals_rs::challenges;
+
+const FUNKY_MUSIC: &str = include_str!("data/funky_music.txt");
+
+const ROLLIN: &str = "Rollin' in my 5.0\nWith my rag-top down so my hair can blow\nThe girlies on standby waving just to say hi\nDid you stop? No, I just drove by\n";
+
+fn answer(number: u32) -> String {
+    challenges::find(number).unwrap().run().unwrap().answer
+}
+
+#[test]
+fn test_registry() {
+    let numbers: Vec<u32> = challenges::registry()
+        .iter()
+        .map(|challenge| challenge.number())
+        .collect();
+    assert_eq!(numbers, (1..=15).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_challenge_1() {
+    assert_eq!(
+        answer(1),
+        "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t"
+    );
+}
+
+#[test]
+fn test_challenge_2() {
+    assert_eq!(answer(2), "746865206b696420646f6e277420706c6179");
+}
+
+#[test]
+fn test_challenge_3() {
+    assert_eq!(answer(3), "Cooking MC's like a pound of bacon");
+}
+
+#[test]
+fn test_challenge_4() {
+    assert_eq!(answer(4), "Now that the party is jumping\n");
+}
+
+#[test]
+fn test_challenge_5() {
+    assert_eq!(
+        answer(5),
+        "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f"
+    );
+}
+
+#[test]
+fn test_challenge_6() {
+    assert_eq!(answer(6), "Terminator X: Bring the noise");
+}
+
+#[test]
+fn test_challenge_7() {
+    assert_eq!(answer(7), FUNKY_MUSIC);
+}
+
+#[test]
+fn test_challenge_8() {
+    assert_eq!(answer(8), "133");
+}
+
+#[test]
+fn test_challenge_9() {
+    assert_eq!(answer(9), "59454c4c4f57205355424d4152494e4504040404");
+}
+
+#[test]
+fn test_challenge_10() {
+    assert_eq!(answer(10), FUNKY_MUSIC);
+}
+
+// the mode is picked at random, and the challenge checks its own guess
+#[test]
+fn test_challenge_11() {
+    for _ in 0..8 {
+        assert!(["ECB", "CBC"].contains(&answer(11).as_str()));
+    }
+}
+
+#[test]
+fn test_challenge_12() {
+    assert_eq!(answer(12), ROLLIN);
+}
+
+#[test]
+fn test_challenge_13() {
+    assert_eq!(answer(13), "admin");
+}
+
+#[test]
+fn test_challenge_14() {
+    assert_eq!(answer(14), ROLLIN);
+}
+
+#[test]
+fn test_challenge_15() {
+    assert_eq!(answer(15), "ok");
+}